@@ -6,6 +6,7 @@ use proc_macro::TokenStream;
 use quote::quote;
 use syn::{
   TraitItem,
+  TraitItemMethod,
   Variant,
   Signature,
   FnArg,
@@ -26,59 +27,182 @@ fn derive_variante(name: &Ident, param: &Type) -> Variant {
 
 /// Produce the match pattern of the rpc requests. Each request
 /// is handled by the rpc implementation and wrapped around
-/// a response message.
-fn derive_handler_arm(sig: &Signature, name: &Ident) -> Arm {
+/// a response message. The interceptors know the request by the
+/// name of its variant, and the metrics by the name of the service
+/// and of the method.
+fn derive_handler_arm(service: &Ident, sig: &Signature, name: &Ident) -> Arm {
   let func_name = &sig.ident;
+  let service = service.to_string();
+  let method = func_name.to_string();
+  let request = name.to_string();
 
   syn::parse_quote! {
     ClientData::#name(arg) => {
-      let result = self.#func_name(ctx, arg);
+      let result = rpc::instrument::call(#service, #method, #request, ctx, |ctx| self.#func_name(ctx, arg));
 
       match result {
-        Ok(value) => ServerData::#name(value),
-        Err(e) => ServerData::Error(e),
+        Ok(Ok(value)) => ServerData::#name(value),
+        Ok(Err(e)) => ServerData::Error(e),
+        Err(rejection) => ServerData::Rejected(rejection.get_message().to_string()),
       }
     }
   }
 }
 
-/// Produce the client functions that will make the requests to
-/// the servers.
-fn derive_client_func(sig: &Signature, name: &Ident, param: &Type, out: &Type, err_type: &Type) -> ItemFn {
+/// Produce the match pattern of the rpc requests for an asynchronous
+/// service. The service is shared by the futures so it is borrowed from
+/// the reference counter and asynchronous functions are awaited.
+fn derive_async_handler_arm(service: &Ident, sig: &Signature, name: &Ident) -> Arm {
   let func_name = &sig.ident;
+  let service = service.to_string();
+  let method = func_name.to_string();
+  let request = name.to_string();
+  let call = if sig.asyncness.is_some() {
+    quote! { rpc::instrument::call_async(#service, #method, #request, ctx, |ctx| this.#func_name(ctx, arg)).await }
+  } else {
+    quote! { rpc::instrument::call(#service, #method, #request, ctx, |ctx| this.#func_name(ctx, arg)) }
+  };
 
   syn::parse_quote! {
-    pub fn #func_name(&self, arg: #param) -> Result<#out, #err_type> {
-      let data = self.send_message(ClientData::#name(arg))?;
+    ClientData::#name(arg) => {
+      let result = #call;
 
-      match data {
-        ServerData::#name(value) => Ok(value),
-        ServerData::Error(e) => Err(e),
-        _ => panic!("invalid response type"),
+      match result {
+        Ok(Ok(value)) => ServerData::#name(value),
+        Ok(Err(e)) => ServerData::Error(e),
+        Err(rejection) => ServerData::Rejected(rejection.get_message().to_string()),
       }
     }
   }
 }
 
+/// Produce the client functions that will make the requests to
+/// the servers, with and without a deadline or other options.
+fn derive_client_func(sig: &Signature, name: &Ident, param: &Type, out: &Type, err_type: &Type) -> Vec<ItemFn> {
+  let func_name = &sig.ident;
+  let deadline_name = Ident::new(&format!("{}_with_deadline", func_name), func_name.span());
+  let options_name = Ident::new(&format!("{}_with_options", func_name), func_name.span());
+
+  vec![
+    syn::parse_quote! {
+      pub fn #func_name(&self, arg: #param) -> Result<#out, #err_type> {
+        self.#deadline_name(arg, None)
+      }
+    },
+    syn::parse_quote! {
+      /// The client stops waiting for the reply when the deadline elapses
+      /// and the server is told about it.
+      pub fn #deadline_name(&self, arg: #param, deadline: Option<std::time::Instant>) -> Result<#out, #err_type> {
+        let opts = rpc::CallOptions { deadline, ..Default::default() };
+
+        self.#options_name(arg, &opts).map(|(value, _)| value)
+      }
+    },
+    syn::parse_quote! {
+      /// The metadata of the options is sent along with the one of the
+      /// client, and the reply comes with the metadata of the server.
+      pub fn #options_name(&self, arg: #param, opts: &rpc::CallOptions) -> Result<(#out, rpc::Metadata), #err_type> {
+        let (data, metadata) = self.send_message(ClientData::#name(arg), opts)?;
+
+        match data {
+          ServerData::#name(value) => Ok((value, metadata)),
+          ServerData::Error(e) => Err(e),
+          ServerData::Rejected(reason) => Err(rpc::transport::CallError::Rejected(reason).into()),
+          _ => panic!("invalid response type"),
+        }
+      }
+    },
+  ]
+}
+
+/// Produce the asynchronous client functions that will make the requests
+/// to the servers, with and without a deadline or other options.
+fn derive_async_client_func(sig: &Signature, name: &Ident, param: &Type, out: &Type, err_type: &Type) -> Vec<ItemFn> {
+  let func_name = &sig.ident;
+  let deadline_name = Ident::new(&format!("{}_with_deadline", func_name), func_name.span());
+  let options_name = Ident::new(&format!("{}_with_options", func_name), func_name.span());
+
+  vec![
+    syn::parse_quote! {
+      pub async fn #func_name(&self, arg: #param) -> Result<#out, #err_type> {
+        self.#deadline_name(arg, None).await
+      }
+    },
+    syn::parse_quote! {
+      /// The client stops waiting for the reply when the deadline elapses
+      /// and the server is told about it.
+      pub async fn #deadline_name(&self, arg: #param, deadline: Option<std::time::Instant>) -> Result<#out, #err_type> {
+        let opts = rpc::CallOptions { deadline, ..Default::default() };
+
+        self.#options_name(arg, &opts).await.map(|(value, _)| value)
+      }
+    },
+    syn::parse_quote! {
+      /// The metadata of the options is sent along with the one of the
+      /// client, and the reply comes with the metadata of the server.
+      pub async fn #options_name(&self, arg: #param, opts: &rpc::CallOptions) -> Result<(#out, rpc::Metadata), #err_type> {
+        let (data, metadata) = self.send_message(ClientData::#name(arg), opts).await?;
+
+        match data {
+          ServerData::#name(value) => Ok((value, metadata)),
+          ServerData::Error(e) => Err(e),
+          ServerData::Rejected(reason) => Err(rpc::transport::CallError::Rejected(reason).into()),
+          _ => panic!("invalid response type"),
+        }
+      }
+    },
+  ]
+}
+
+/// Turn an asynchronous function into a function returning a future that
+/// can be sent across threads so that the service can be run by any
+/// runtime. Implementations can still be written with async functions.
+fn desugar_async_method(m: &mut TraitItemMethod) {
+  m.sig.asyncness = None;
+
+  let out = match &m.sig.output {
+    syn::ReturnType::Type(_, t) => quote! { #t },
+    syn::ReturnType::Default => quote! { () },
+  };
+
+  m.sig.output = syn::parse_quote! {
+    -> impl std::future::Future<Output = #out> + Send
+  };
+}
+
+/// Names of the methods whose variant would clash with the variants that
+/// ServerData has for every service.
+const RESERVED_METHODS: [&str; 2] = ["error", "rejected"];
+
+/// Derive the messages, the processor and the clients of the service. The
+/// replies of the methods share the ServerData enumeration with the error
+/// of the service and the rejection of an interceptor, so that a service
+/// can't have a method named `error` or `rejected`.
 #[proc_macro_attribute]
 pub fn service(_: TokenStream, item: TokenStream) -> TokenStream {
   let input = syn::parse_macro_input!(item as syn::ItemTrait);
 
   let name_service = &input.ident;
-  let client_name = Ident::new(format!("{}Client", name_service).as_ref(), syn::export::Span::call_site());
+  let client_name = Ident::new(format!("{}Client", name_service).as_ref(), proc_macro2::Span::call_site());
   let mut requests: Punctuated<Variant, Comma> = Punctuated::new();
   let mut responses: Punctuated<Variant, Comma> = Punctuated::new();
   let mut methods: Vec<TraitItem> = Vec::new();
   let mut handlers: Vec<Arm> = Vec::new();
+  let mut async_handlers: Vec<Arm> = Vec::new();
   let mut client_funcs: Vec<ItemFn> = Vec::new();
+  let mut async_client_funcs: Vec<ItemFn> = Vec::new();
   let mut err_type = None;
+  let mut is_async = false;
 
   for method in input.items {
     match method {
       TraitItem::Method(m) => {
         let name = m.sig.ident.to_string();
+        if RESERVED_METHODS.contains(&name.as_str()) {
+          panic!("method name `{}` is reserved", name);
+        }
         let name = name[0..1].to_uppercase() + &name[1..];
-        let name = &Ident::new(name.as_ref(), syn::export::Span::call_site());
+        let name = &Ident::new(name.as_ref(), proc_macro2::Span::call_site());
 
         let param: &Type;
         if let FnArg::Typed(ref pt) = &m.sig.inputs[2] {
@@ -111,9 +235,17 @@ pub fn service(_: TokenStream, item: TokenStream) -> TokenStream {
 
         requests.push(derive_variante(name, param));
         responses.push(derive_variante(name, out));
-        methods.push(TraitItem::Method(m.clone()));
-        handlers.push(derive_handler_arm(&m.sig, name));
-        client_funcs.push(derive_client_func(&m.sig, name, param, out, &err_type));
+        handlers.push(derive_handler_arm(name_service, &m.sig, name));
+        async_handlers.push(derive_async_handler_arm(name_service, &m.sig, name));
+        client_funcs.extend(derive_client_func(&m.sig, name, param, out, &err_type));
+        async_client_funcs.extend(derive_async_client_func(&m.sig, name, param, out, &err_type));
+
+        let mut m = m.clone();
+        if m.sig.asyncness.is_some() {
+          is_async = true;
+          desugar_async_method(&mut m);
+        }
+        methods.push(TraitItem::Method(m));
       },
       _ => (), // only interested in methods
     }
@@ -121,6 +253,54 @@ pub fn service(_: TokenStream, item: TokenStream) -> TokenStream {
 
   let err_type = err_type.expect("rpc function expects an error.");
 
+  if is_async && !cfg!(feature = "async") {
+    panic!("async rpc functions require the `async` feature");
+  }
+
+  // A service with at least one asynchronous function produces a processor
+  // returning futures, which is served by the asynchronous transports.
+  let processor = if is_async {
+    quote! {
+      pub type RequestProcessor = dyn Fn(ClientData, Context) -> std::pin::Pin<Box<dyn std::future::Future<Output = ServerData> + Send>> + Send + Sync;
+
+      pub trait #name_service: Sized + Sync + Send + RefUnwindSafe + 'static {
+        #(#methods)*
+
+        fn get_processor(self) -> Box<RequestProcessor> {
+          let this = std::sync::Arc::new(self);
+
+          Box::new(move |msg, ctx| {
+            let this = std::sync::Arc::clone(&this);
+
+            Box::pin(async move {
+              match msg {
+                #(#async_handlers),*
+              }
+            })
+          })
+        }
+      }
+    }
+  } else {
+    quote! {
+      pub type RequestProcessor = dyn Fn(ClientData, Context) -> ServerData + Send + Sync + RefUnwindSafe;
+
+      pub trait #name_service: Sized + Sync + Send + RefUnwindSafe + 'static {
+        #(#methods)*
+
+        fn get_processor(self) -> Box<RequestProcessor> {
+          Box::new(move |msg, ctx| {
+            match msg {
+              #(#handlers),*
+            }
+          })
+        }
+      }
+    }
+  };
+
+  let async_client = derive_async_client(&client_name, &err_type, &async_client_funcs);
+
   let result = quote! {
     use serde::{Deserialize, Serialize};
     use rpc::transport::ClientTransport;
@@ -134,50 +314,99 @@ pub fn service(_: TokenStream, item: TokenStream) -> TokenStream {
     pub enum ClientData { #requests }
 
     /// ServerData enumerates the list of possible response messages sent
-    /// by the server to a client after processing a request.
+    /// by the server to a client after processing a request. A request
+    /// refused by an interceptor is answered with the reason.
     #[derive(Serialize, Deserialize, Debug)]
     pub enum ServerData {
       Error(#err_type),
+      Rejected(String),
       #responses
     }
 
-    pub type RequestProcessor = dyn Fn(ClientData, Address, Address) -> ServerData + Send + Sync + RefUnwindSafe;
-
-    pub trait #name_service: Sized + Sync + Send + RefUnwindSafe + 'static {
-      #(#methods)*
-
-      fn get_processor(self) -> Box<RequestProcessor> {
-        Box::new(move |msg, out_addr, in_addr| {
-          let ctx = Context::new(in_addr, out_addr);
-
-          match msg {
-            #(#handlers),*
-          }
-        })
-      }
-    }
+    #processor
 
     pub struct #client_name<T> {
       t: T,
+      metadata: rpc::Metadata,
     }
 
     impl<T> #client_name<T>
     where
       T: ClientTransport<ClientData, ServerData>,
+      T::Error: 'static,
     {
       pub fn new(t: T) -> #client_name<T> {
-        #client_name { t }
+        #client_name { t, metadata: rpc::Metadata::new() }
       }
 
-      fn send_message(&self, msg: ClientData) -> Result<ServerData, #err_type> {
-        let res = self.t.send(msg)?;
-        
+      /// Set the metadata sent with every request of the client.
+      pub fn with_metadata(mut self, metadata: rpc::Metadata) -> Self {
+        self.metadata = metadata;
+        self
+      }
+
+      fn send_message(&self, msg: ClientData, opts: &rpc::CallOptions) -> Result<(ServerData, rpc::Metadata), #err_type> {
+        let mut metadata = self.metadata.clone();
+        metadata.extend(opts.metadata.clone());
+
+        let opts = rpc::CallOptions { deadline: opts.deadline, metadata };
+        let res = self.t.send_with_options(msg, &opts).map_err(rpc::transport::CallError::from_transport)?;
+
         Ok(res)
       }
 
       #(#client_funcs)*
     }
+
+    #async_client
   };
 
   result.into()
 }
+
+/// Produce the asynchronous client which is only available with the
+/// `async` feature.
+#[cfg(feature = "async")]
+fn derive_async_client(client_name: &Ident, err_type: &Type, funcs: &[ItemFn]) -> proc_macro2::TokenStream {
+  let async_client_name = Ident::new(format!("Async{}", client_name).as_ref(), proc_macro2::Span::call_site());
+
+  quote! {
+    pub struct #async_client_name<T> {
+      t: T,
+      metadata: rpc::Metadata,
+    }
+
+    impl<T> #async_client_name<T>
+    where
+      T: rpc::transport::AsyncClientTransport<ClientData, ServerData>,
+      T::Error: 'static,
+    {
+      pub fn new(t: T) -> #async_client_name<T> {
+        #async_client_name { t, metadata: rpc::Metadata::new() }
+      }
+
+      /// Set the metadata sent with every request of the client.
+      pub fn with_metadata(mut self, metadata: rpc::Metadata) -> Self {
+        self.metadata = metadata;
+        self
+      }
+
+      async fn send_message(&self, msg: ClientData, opts: &rpc::CallOptions) -> Result<(ServerData, rpc::Metadata), #err_type> {
+        let mut metadata = self.metadata.clone();
+        metadata.extend(opts.metadata.clone());
+
+        let opts = rpc::CallOptions { deadline: opts.deadline, metadata };
+        let res = self.t.send_with_options(msg, &opts).await.map_err(rpc::transport::CallError::from_transport)?;
+
+        Ok(res)
+      }
+
+      #(#funcs)*
+    }
+  }
+}
+
+#[cfg(not(feature = "async"))]
+fn derive_async_client(_: &Ident, _: &Type, _: &[ItemFn]) -> proc_macro2::TokenStream {
+  proc_macro2::TokenStream::new()
+}
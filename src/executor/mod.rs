@@ -0,0 +1,310 @@
+mod shared;
+mod spawner;
+mod stealing;
+
+pub use self::shared::Shared;
+pub use self::spawner::{Inline, Spawner, Task};
+pub use self::stealing::StealingPool;
+
+use std::io;
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{Builder, JoinHandle};
+use std::time::Instant;
+
+/// Job submitted to an executor. The error is only logged by the worker.
+pub type Job = Box<dyn FnOnce() -> io::Result<()> + Send + panic::UnwindSafe + 'static>;
+
+/// An executor runs the jobs of a server, usually on a pool of threads.
+pub trait Executor: Send + Sync + 'static {
+    /// Queue the job, waiting for room in the queue when it is full.
+    fn execute(&self, job: Job) -> io::Result<()>;
+
+    /// Queue the job unless the queue is full, in which case an error of
+    /// kind WouldBlock is returned and the job is dropped. Executors
+    /// without a limit on the queue never refuse a job.
+    fn try_execute(&self, job: Job) -> io::Result<()> {
+        self.execute(job)
+    }
+
+    /// Wait for the jobs queued or running to complete until the deadline.
+    /// The jobs that have not started by then are discarded. It returns the
+    /// number of jobs that did not complete in time.
+    fn shutdown(&self, deadline: Instant) -> usize;
+}
+
+/// Number of jobs waiting for a worker and being run by one.
+#[derive(Default)]
+struct Jobs {
+    queued: usize,
+    running: usize,
+}
+
+impl Jobs {
+    fn pending(&self) -> usize {
+        self.queued + self.running
+    }
+}
+
+/// State shared by a pool and its workers to know how many jobs are
+/// queued or running.
+struct State {
+    jobs: Mutex<Jobs>,
+    done: Condvar,
+    room: Condvar,
+    aborted: AtomicBool,
+}
+
+impl State {
+    fn new() -> State {
+        State {
+            jobs: Mutex::new(Jobs::default()),
+            done: Condvar::new(),
+            room: Condvar::new(),
+            aborted: AtomicBool::new(false),
+        }
+    }
+
+    fn is_full(jobs: &Jobs, max_queued: Option<usize>) -> bool {
+        match max_queued {
+            Some(max) => jobs.queued >= max,
+            None => false,
+        }
+    }
+
+    /// Wait for room in the queue and return the counters so that the job
+    /// can be queued.
+    fn wait_room(&self, max_queued: Option<usize>) -> MutexGuard<'_, Jobs> {
+        let mut jobs = self.jobs.lock().unwrap();
+        while State::is_full(&jobs, max_queued) {
+            jobs = self.room.wait(jobs).unwrap();
+        }
+
+        jobs
+    }
+
+    /// Return the counters so that the job can be queued, or an error when
+    /// the queue is full.
+    fn try_room(&self, max_queued: Option<usize>) -> io::Result<MutexGuard<'_, Jobs>> {
+        let jobs = self.jobs.lock().unwrap();
+        if State::is_full(&jobs, max_queued) {
+            return Err(io::Error::new(io::ErrorKind::WouldBlock, "queue of jobs is full"));
+        }
+
+        Ok(jobs)
+    }
+
+    fn queue(&self, mut jobs: MutexGuard<Jobs>) {
+        jobs.queued += 1;
+    }
+
+    /// Forget a queued job that could not be handed to a worker.
+    fn cancel(&self) {
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.queued -= 1;
+
+        self.room.notify_one();
+        if jobs.pending() == 0 {
+            self.done.notify_all();
+        }
+    }
+
+    fn start(&self) {
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.queued -= 1;
+        jobs.running += 1;
+
+        self.room.notify_one();
+    }
+
+    fn finish(&self) {
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.running -= 1;
+
+        if jobs.pending() == 0 {
+            self.done.notify_all();
+        }
+    }
+
+    /// Run a job taken from the queue by the worker.
+    fn run(&self, worker: usize, job: Job) {
+        self.start();
+
+        // The pool has been shut down so the remaining jobs are dropped
+        // without running.
+        if self.is_aborted() {
+            self.finish();
+            return;
+        }
+
+        // Note: some panic might not be unwind
+        let res = panic::catch_unwind(job);
+        self.finish();
+
+        match res {
+            Err(_) => {
+                tracing::error!(worker, "worker caught a panic");
+            },
+            Ok(res) => {
+                if let Err(e) = res {
+                    tracing::warn!(worker, error = %e, "worker failed a job");
+                }
+            }
+        }
+    }
+
+    fn shutdown(&self, deadline: Instant) -> usize {
+        let mut jobs = self.jobs.lock().unwrap();
+
+        while jobs.pending() > 0 {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+
+            jobs = self.done.wait_timeout(jobs, deadline - now).unwrap().0;
+        }
+
+        if jobs.pending() > 0 {
+            self.aborted.store(true, Ordering::SeqCst);
+        }
+
+        jobs.pending()
+    }
+
+    fn is_aborted(&self) -> bool {
+        self.aborted.load(Ordering::SeqCst)
+    }
+}
+
+/// Pool with a fixed number of workers taking the jobs from a single
+/// queue.
+pub struct ThreadPool {
+    workers: Vec<Worker>,
+    sender: Option<mpsc::Sender<Job>>,
+    state: Arc<State>,
+    max_queued: Option<usize>,
+}
+
+impl ThreadPool {
+    /// Create a pool with the given number of workers and no limit on the
+    /// number of jobs waiting for one of them.
+    pub fn new(size: usize) -> ThreadPool {
+        assert!(size > 0);
+
+        let mut workers = Vec::with_capacity(size);
+        let (sender, receiver) = mpsc::channel();
+
+        let receiver = Arc::new(Mutex::new(receiver));
+        let state = Arc::new(State::new());
+
+        for id in 0..size {
+            workers.push(Worker::new(id, receiver.clone(), state.clone()));
+        }
+
+        ThreadPool {
+            workers,
+            sender: Some(sender),
+            state,
+            max_queued: None,
+        }
+    }
+
+    /// Create a pool with the given number of workers where at most
+    /// `max_queued` jobs can wait for one of them. It panics when the queue
+    /// can't hold a single job.
+    pub fn with_max_queued(size: usize, max_queued: usize) -> ThreadPool {
+        assert!(max_queued > 0);
+
+        let mut pool = ThreadPool::new(size);
+        pool.max_queued = Some(max_queued);
+
+        pool
+    }
+
+    fn send(&self, jobs: MutexGuard<Jobs>, job: Job) -> io::Result<()> {
+        let sender = self.sender.as_ref().unwrap();
+
+        self.state.queue(jobs);
+
+        if let Err(ref e) = sender.send(job) {
+            self.state.cancel();
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, e.to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+impl Executor for ThreadPool {
+    fn execute(&self, job: Job) -> io::Result<()> {
+        let jobs = self.state.wait_room(self.max_queued);
+
+        self.send(jobs, job)
+    }
+
+    fn try_execute(&self, job: Job) -> io::Result<()> {
+        let jobs = self.state.try_room(self.max_queued)?;
+
+        self.send(jobs, job)
+    }
+
+    fn shutdown(&self, deadline: Instant) -> usize {
+        self.state.shutdown(deadline)
+    }
+}
+
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        self.sender = None;
+
+        // Workers still running a job after the deadline of a shutdown are
+        // left behind as they could block for too long.
+        if self.state.is_aborted() {
+            return;
+        }
+
+        let mut ids = Vec::new();
+
+        for worker in self.workers.drain(..) {
+            match worker.thread.join() {
+                Ok(_) => ids.push(worker.id),
+                Err(e) => tracing::error!(worker = worker.id, error = ?e, "worker couldn't stop"),
+            };
+        }
+
+        tracing::debug!(workers = ?ids, "workers have been shut down");
+    }
+}
+
+struct Worker {
+    id: usize,
+    thread: JoinHandle<()>,
+}
+
+impl Worker {
+    fn new(id: usize, receiver: Arc<Mutex<mpsc::Receiver<Job>>>, state: Arc<State>) -> Worker {
+        let thread = Builder::new()
+            .name(format!("worker-thread-{}", id))
+            .spawn(move || {
+                loop {
+                    let receiver = receiver.lock().unwrap();
+
+                    if let Ok(job) = receiver.recv() {
+                        // Force to unlock so that any panics occuring in the handler
+                        // won't poison the lock.
+                        drop(receiver);
+
+                        state.run(id, job);
+                    } else {
+                        // Channel has been closed to shutdown.
+                        return;
+                    }
+                }
+            })
+            .unwrap();
+
+        Worker { id, thread }
+    }
+}
@@ -0,0 +1,70 @@
+use super::{Executor, Job, State};
+use std::io;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Executor submitting the jobs to a pool shared with other servers.
+///
+/// It keeps track of the jobs it submits so that the shutdown of a server
+/// only waits for, and aborts, the jobs of that server while the pool keeps
+/// running the ones of the others.
+///
+/// ```ignore
+/// let pool = Arc::new(ThreadPool::new(4));
+/// let executor = Shared::new(Arc::clone(&pool));
+/// ```
+pub struct Shared<E: ?Sized> {
+    executor: Arc<E>,
+    state: Arc<State>,
+}
+
+impl<E: Executor + ?Sized> Shared<E> {
+    /// Create an executor that submits the jobs to the shared one.
+    pub fn new(executor: Arc<E>) -> Shared<E> {
+        Shared {
+            executor,
+            state: Arc::new(State::new()),
+        }
+    }
+
+    /// Wrap the job so that it is counted as a job of this executor.
+    fn wrap(&self, job: Job) -> Job {
+        let state = Arc::clone(&self.state);
+        // The pool has no notion of the workers of this executor so every
+        // job is reported as being run by the first one.
+        Box::new(move || {
+            state.run(0, job);
+            Ok(())
+        })
+    }
+}
+
+impl<E: Executor + ?Sized> Executor for Shared<E> {
+    fn execute(&self, job: Job) -> io::Result<()> {
+        self.state.queue(self.state.wait_room(None));
+
+        let res = self.executor.execute(self.wrap(job));
+        if res.is_err() {
+            self.state.cancel();
+        }
+
+        res
+    }
+
+    fn try_execute(&self, job: Job) -> io::Result<()> {
+        self.state.queue(self.state.wait_room(None));
+
+        let res = self.executor.try_execute(self.wrap(job));
+        if res.is_err() {
+            self.state.cancel();
+        }
+
+        res
+    }
+
+    /// Wait for the jobs of this executor only. The shared pool keeps
+    /// running.
+    fn shutdown(&self, deadline: Instant) -> usize {
+        self.state.shutdown(deadline)
+    }
+}
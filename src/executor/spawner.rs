@@ -0,0 +1,92 @@
+use super::{Executor, Job, State};
+use std::io;
+use std::panic::UnwindSafe;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Task handed to the runtime of a spawner. It never panics as the job
+/// it wraps is run behind a catch_unwind.
+pub type Task = Box<dyn FnOnce() + Send + UnwindSafe + 'static>;
+
+/// Executor running the jobs on a runtime supplied by the user, e.g. a
+/// rayon pool or the blocking pool of tokio, so that a server doesn't need
+/// threads of its own.
+///
+/// The spawner keeps track of the jobs it gives to the runtime so that a
+/// shutdown only waits for the jobs of the server, even if the runtime is
+/// shared by others.
+///
+/// ```ignore
+/// let executor = Spawner::new(|task| rayon::spawn(task));
+/// ```
+pub struct Spawner<F> {
+    spawn: F,
+    state: Arc<State>,
+}
+
+impl<F> Spawner<F>
+where
+    F: Fn(Task) + Send + Sync + 'static,
+{
+    /// Create an executor that hands the jobs to the spawn function.
+    pub fn new(spawn: F) -> Spawner<F> {
+        Spawner {
+            spawn,
+            state: Arc::new(State::new()),
+        }
+    }
+}
+
+impl<F> Executor for Spawner<F>
+where
+    F: Fn(Task) + Send + Sync + 'static,
+{
+    fn execute(&self, job: Job) -> io::Result<()> {
+        self.state.queue(self.state.wait_room(None));
+
+        let state = Arc::clone(&self.state);
+        // The runtime has no notion of workers so every job is reported as
+        // being run by the first one.
+        (self.spawn)(Box::new(move || state.run(0, job)));
+
+        Ok(())
+    }
+
+    fn shutdown(&self, deadline: Instant) -> usize {
+        self.state.shutdown(deadline)
+    }
+}
+
+/// Executor running the jobs on the thread that submits them, which is the
+/// thread reading the connection for a server. The requests of a connection
+/// are then processed one after the other. It is mostly useful for tests.
+pub struct Inline {
+    state: State,
+}
+
+impl Inline {
+    pub fn new() -> Inline {
+        Inline {
+            state: State::new(),
+        }
+    }
+}
+
+impl Default for Inline {
+    fn default() -> Self {
+        Inline::new()
+    }
+}
+
+impl Executor for Inline {
+    fn execute(&self, job: Job) -> io::Result<()> {
+        self.state.queue(self.state.wait_room(None));
+        self.state.run(0, job);
+
+        Ok(())
+    }
+
+    fn shutdown(&self, deadline: Instant) -> usize {
+        self.state.shutdown(deadline)
+    }
+}
@@ -0,0 +1,262 @@
+use super::{Executor, Job, Jobs, State};
+use crossbeam_deque::{Injector, Steal, Stealer, Worker};
+use std::io;
+use std::iter;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, RwLock};
+use std::thread::{Builder, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Duration after which a worker above the minimum stops when no job comes.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Pool where each worker has its own queue of jobs and steals from the
+/// others when it runs out of them, so that the workers don't contend on a
+/// single queue. The number of workers grows from the minimum to the
+/// maximum when every worker is busy, and the extra workers are stopped
+/// once they have been idle for a while.
+pub struct StealingPool {
+    shared: Arc<Shared>,
+    threads: Mutex<Vec<JoinHandle<()>>>,
+    max: usize,
+    max_queued: Option<usize>,
+}
+
+/// Workers of the pool and the settings to stop them.
+struct Sleep {
+    threads: usize,
+    idle: usize,
+    /// Idle workers that have been woken up but are not running yet.
+    notified: usize,
+    next_id: usize,
+    min: usize,
+    idle_timeout: Duration,
+    stopping: bool,
+}
+
+struct Shared {
+    state: State,
+    injector: Injector<Job>,
+    stealers: RwLock<Vec<(usize, Stealer<Job>)>>,
+    sleep: Mutex<Sleep>,
+    wake: Condvar,
+}
+
+impl Shared {
+    /// Return true when a job is waiting for a worker.
+    fn has_work(&self) -> bool {
+        !self.injector.is_empty() || self.stealers.read().unwrap().iter().any(|(_, s)| !s.is_empty())
+    }
+
+    /// Take the next job from the queue of the worker, or from the global
+    /// queue, or from the queue of another worker.
+    fn find_job(&self, local: &Worker<Job>) -> Option<Job> {
+        if let Some(job) = local.pop() {
+            return Some(job);
+        }
+
+        let job = iter::repeat_with(|| {
+            self.injector.steal_batch_and_pop(local).or_else(|| {
+                self.stealers.read().unwrap().iter().map(|(_, s)| s.steal()).collect::<Steal<Job>>()
+            })
+        })
+        .find(|s| !s.is_retry())
+        .and_then(|s| s.success());
+
+        // The batch taken from the global queue can be stolen by a worker
+        // sleeping otherwise.
+        if job.is_some() && !local.is_empty() {
+            let mut sleep = self.sleep.lock().unwrap();
+            if sleep.idle > sleep.notified {
+                sleep.notified += 1;
+                self.wake.notify_one();
+            }
+        }
+
+        job
+    }
+
+    /// Wait for a job to come. It returns false when the worker must stop.
+    fn wait(&self) -> bool {
+        let mut sleep = self.sleep.lock().unwrap();
+
+        loop {
+            if self.has_work() {
+                return true;
+            }
+
+            if sleep.stopping {
+                sleep.threads -= 1;
+                return false;
+            }
+
+            sleep.idle += 1;
+            let idle_timeout = sleep.idle_timeout;
+            let (guard, res) = self.wake.wait_timeout(sleep, idle_timeout).unwrap();
+            sleep = guard;
+            sleep.idle -= 1;
+            // Every wake-up counts as the notification it might have raced
+            // with, so that the count never exceeds the workers still idle
+            // and a new job always wakes one of them.
+            if sleep.notified > 0 {
+                sleep.notified -= 1;
+            }
+            sleep.notified = sleep.notified.min(sleep.idle);
+
+            if res.timed_out() && sleep.threads > sleep.min && !self.has_work() {
+                sleep.threads -= 1;
+                return false;
+            }
+        }
+    }
+}
+
+impl StealingPool {
+    /// Create a pool that keeps at least `min` workers and grows up to
+    /// `max` workers when they are all busy.
+    pub fn new(min: usize, max: usize) -> StealingPool {
+        assert!(max > 0 && min <= max);
+
+        let pool = StealingPool {
+            shared: Arc::new(Shared {
+                state: State::new(),
+                injector: Injector::new(),
+                stealers: RwLock::new(Vec::new()),
+                sleep: Mutex::new(Sleep {
+                    threads: 0,
+                    idle: 0,
+                    notified: 0,
+                    next_id: 0,
+                    min,
+                    idle_timeout: IDLE_TIMEOUT,
+                    stopping: false,
+                }),
+                wake: Condvar::new(),
+            }),
+            threads: Mutex::new(Vec::new()),
+            max,
+            max_queued: None,
+        };
+
+        {
+            let mut sleep = pool.shared.sleep.lock().unwrap();
+            for _ in 0..min {
+                pool.spawn(&mut sleep);
+            }
+        }
+
+        pool
+    }
+
+    /// Set the duration after which a worker above the minimum is stopped
+    /// when no job comes.
+    pub fn with_idle_timeout(self, idle_timeout: Duration) -> StealingPool {
+        self.shared.sleep.lock().unwrap().idle_timeout = idle_timeout;
+        // The idle workers wait again with the new timeout.
+        self.shared.wake.notify_all();
+
+        self
+    }
+
+    /// Set the maximum number of jobs waiting for a worker. It panics when
+    /// the queue can't hold a single job.
+    pub fn with_max_queued(mut self, max_queued: usize) -> StealingPool {
+        assert!(max_queued > 0);
+
+        self.max_queued = Some(max_queued);
+
+        self
+    }
+
+    /// Return the number of workers currently running.
+    pub fn get_threads(&self) -> usize {
+        self.shared.sleep.lock().unwrap().threads
+    }
+
+    fn spawn(&self, sleep: &mut Sleep) {
+        let id = sleep.next_id;
+        sleep.next_id += 1;
+        sleep.threads += 1;
+
+        let local = Worker::new_fifo();
+        self.shared.stealers.write().unwrap().push((id, local.stealer()));
+
+        let shared = Arc::clone(&self.shared);
+
+        let thread = Builder::new()
+            .name(format!("worker-thread-{}", id))
+            .spawn(move || {
+                loop {
+                    match shared.find_job(&local) {
+                        Some(job) => shared.state.run(id, job),
+                        None if shared.wait() => (),
+                        None => break,
+                    }
+                }
+
+                shared.stealers.write().unwrap().retain(|(i, _)| *i != id);
+                tracing::debug!(worker = id, "worker has stopped");
+            })
+            .unwrap();
+
+        let mut threads = self.threads.lock().unwrap();
+        // Forget the workers that have stopped after being idle.
+        threads.retain(|t| !t.is_finished());
+        threads.push(thread);
+    }
+
+    fn push(&self, jobs: MutexGuard<Jobs>, job: Job) {
+        self.shared.state.queue(jobs);
+        self.shared.injector.push(job);
+
+        let mut sleep = self.shared.sleep.lock().unwrap();
+        if sleep.idle > sleep.notified {
+            sleep.notified += 1;
+            self.shared.wake.notify_one();
+        } else if sleep.threads < self.max {
+            self.spawn(&mut sleep);
+        }
+    }
+}
+
+impl Executor for StealingPool {
+    fn execute(&self, job: Job) -> io::Result<()> {
+        let jobs = self.shared.state.wait_room(self.max_queued);
+
+        self.push(jobs, job);
+
+        Ok(())
+    }
+
+    fn try_execute(&self, job: Job) -> io::Result<()> {
+        let jobs = self.shared.state.try_room(self.max_queued)?;
+
+        self.push(jobs, job);
+
+        Ok(())
+    }
+
+    fn shutdown(&self, deadline: Instant) -> usize {
+        self.shared.state.shutdown(deadline)
+    }
+}
+
+impl Drop for StealingPool {
+    fn drop(&mut self) {
+        self.shared.sleep.lock().unwrap().stopping = true;
+        self.shared.wake.notify_all();
+
+        // Workers still running a job after the deadline of a shutdown are
+        // left behind as they could block for too long.
+        if self.shared.state.is_aborted() {
+            return;
+        }
+
+        for thread in self.threads.lock().unwrap().drain(..) {
+            if let Err(e) = thread.join() {
+                tracing::error!(error = ?e, "worker couldn't stop");
+            }
+        }
+
+        tracing::debug!("workers have been shut down");
+    }
+}
@@ -3,21 +3,26 @@ use std::fmt;
 use std::io;
 use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
 use std::option::IntoIter;
+use std::path::PathBuf;
 use std::str::FromStr;
 
+/// Prefix of the string representation of a Unix domain socket address.
+const UNIX_PREFIX: &str = "unix:";
+
 /// Address can be local or distant. A local address will have
 /// a unique identifier and a distant address will have an ip
-/// and a port.
+/// and a port, or the path of a Unix domain socket.
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub enum Address {
     Local(String),
     Socket(SocketAddr),
+    Unix(PathBuf),
 }
 
 impl Address {
     pub fn get_socket_addr(&self) -> Option<SocketAddr> {
         match self {
-            Address::Socket(addr) => Some(addr.clone()),
+            Address::Socket(addr) => Some(*addr),
             _ => None,
         }
     }
@@ -29,9 +34,15 @@ impl Address {
     }
 
     /// Create an address from a string. If it can be parsed
-    /// into a socket address, it will create a distant address
-    /// and a local one for any other case.
+    /// into a socket address, it will create a distant address,
+    /// a string like `unix:/run/x.sock` creates a Unix domain
+    /// socket address and a local one for any other case.
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(addr: &str) -> Address {
+        if let Some(path) = addr.strip_prefix(UNIX_PREFIX) {
+            return Address::Unix(PathBuf::from(path));
+        }
+
         let r = SocketAddr::from_str(addr);
 
         match r {
@@ -46,25 +57,18 @@ impl fmt::Display for Address {
         match self {
             Address::Local(value) => write!(f, "{}", value),
             Address::Socket(addr) => write!(f, "{}", addr),
+            Address::Unix(path) => write!(f, "{}{}", UNIX_PREFIX, path.display()),
         }
     }
 }
 
 impl PartialEq for Address {
     fn eq(&self, other: &Self) -> bool {
-        match self {
-            Address::Local(v) => {
-                if let Address::Local(ov) = other {
-                    return v == ov;
-                }
-                return false;
-            },
-            Address::Socket(addr) => {
-                if let Address::Socket(oa) = other {
-                    return addr == oa;
-                }
-                return false;
-            }
+        match (self, other) {
+            (Address::Local(v), Address::Local(ov)) => v == ov,
+            (Address::Socket(addr), Address::Socket(oa)) => addr == oa,
+            (Address::Unix(path), Address::Unix(op)) => path == op,
+            _ => false,
         }
     }
 }
@@ -74,7 +78,7 @@ impl ToSocketAddrs for Address {
 
     fn to_socket_addrs(&self) -> io::Result<IntoIter<SocketAddr>> {
         match self {
-            Address::Socket(addr) => Ok(Some(addr.clone()).into_iter()),
+            Address::Socket(addr) => Ok(Some(*addr).into_iter()),
             _ => Err(io::Error::new(
                 io::ErrorKind::AddrNotAvailable,
                 "not a socket type address",
@@ -0,0 +1,150 @@
+use super::Context;
+use super::interceptor::{self, Rejection};
+use super::metrics::{Outcome, Recorder};
+use std::fmt::Debug;
+use std::time::Instant;
+use tracing::Span;
+#[cfg(feature = "async")]
+use std::future::Future;
+#[cfg(feature = "async")]
+use tracing::Instrument;
+
+/// Create the span of a request which is identified by the name of the
+/// method and the address of the caller.
+fn request_span(method: &'static str, ctx: &Context) -> Span {
+    tracing::info_span!("request", method, peer = %ctx.get_in_addr())
+}
+
+/// Measure of a request in progress. A request that does not complete
+/// because the thread is unwinding is reported as a panic.
+struct Measure {
+    service: &'static str,
+    method: &'static str,
+    metrics: Recorder,
+    start: Instant,
+    done: bool,
+}
+
+impl Measure {
+    fn new(service: &'static str, method: &'static str, ctx: &Context) -> Measure {
+        Measure {
+            service,
+            method,
+            metrics: ctx.get_metrics().clone(),
+            start: Instant::now(),
+            done: false,
+        }
+    }
+
+    /// Log and record the outcome of the request.
+    fn finish<T, E: Debug>(mut self, res: &Result<T, E>) {
+        self.done = true;
+        let elapsed = self.start.elapsed();
+
+        let outcome = match res {
+            Ok(_) => {
+                tracing::info!(?elapsed, "request succeeded");
+                Outcome::Success
+            }
+            Err(e) => {
+                tracing::warn!(?elapsed, error = ?e, "request failed");
+                Outcome::Error
+            }
+        };
+
+        self.metrics.record(|m| m.record_request(self.service, self.method, outcome, elapsed));
+    }
+}
+
+impl Drop for Measure {
+    fn drop(&mut self) {
+        if !self.done && std::thread::panicking() {
+            let elapsed = self.start.elapsed();
+
+            tracing::error!(?elapsed, "request panicked");
+            self.metrics.record(|m| m.record_request(self.service, self.method, Outcome::Panic, elapsed));
+        }
+    }
+}
+
+/// Outcome of the result of a service.
+fn outcome<T, E>(res: &Result<T, E>) -> Outcome {
+    match res {
+        Ok(_) => Outcome::Success,
+        Err(_) => Outcome::Error,
+    }
+}
+
+fn rejected<T, E>(measure: Measure, rejection: Rejection) -> Result<Result<T, E>, Rejection> {
+    tracing::warn!(reason = rejection.get_message(), "request rejected");
+    measure.finish(&Err::<(), _>(&rejection));
+
+    Err(rejection)
+}
+
+/// Run the function of a service inside the span of the request, after
+/// the interceptors of the context let the request through. The request is
+/// the name of the variant of the message, which is given to the
+/// interceptors, and the request is measured under the names of the service
+/// and of the method. The rejection of an interceptor is returned as is so
+/// that it reaches the client as such.
+pub fn call<T, E, F>(
+    service: &'static str,
+    method: &'static str,
+    request: &'static str,
+    ctx: Context,
+    f: F,
+) -> Result<Result<T, E>, Rejection>
+where
+    E: Debug,
+    F: FnOnce(Context) -> Result<T, E>,
+{
+    let span = request_span(method, &ctx);
+    let _enter = span.enter();
+    let measure = Measure::new(service, method, &ctx);
+
+    let guard = match interceptor::enter(request, &ctx) {
+        Ok(guard) => guard,
+        Err(rejection) => return rejected(measure, rejection),
+    };
+
+    let res = f(ctx);
+    guard.finish(outcome(&res));
+    measure.finish(&res);
+
+    Ok(res)
+}
+
+/// Run the asynchronous function of a service inside the span of the
+/// request, after the interceptors of the context let the request through.
+#[cfg(feature = "async")]
+pub async fn call_async<T, E, F, Fut>(
+    service: &'static str,
+    method: &'static str,
+    request: &'static str,
+    ctx: Context,
+    f: F,
+) -> Result<Result<T, E>, Rejection>
+where
+    E: Debug,
+    F: FnOnce(Context) -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let span = request_span(method, &ctx);
+
+    async move {
+        let measure = Measure::new(service, method, &ctx);
+        let guard = match interceptor::enter(request, &ctx) {
+            Ok(guard) => guard,
+            Err(rejection) => return rejected(measure, rejection),
+        };
+
+        let res = f(ctx).await;
+        guard.finish(outcome(&res));
+        measure.finish(&res);
+
+        Ok(res)
+    }
+    .instrument(span)
+    .await
+}
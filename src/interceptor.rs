@@ -0,0 +1,169 @@
+use super::metrics::Outcome;
+use super::transport::RequestProcessor;
+#[cfg(feature = "async")]
+use super::transport::AsyncRequestProcessor;
+use super::Context;
+use std::fmt;
+use std::panic::RefUnwindSafe;
+use std::sync::Arc;
+
+/// Reason given by an interceptor to refuse a request. The client receives
+/// it as a CallError::Rejected.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rejection {
+    message: String,
+}
+
+impl Rejection {
+    pub fn new(message: impl Into<String>) -> Rejection {
+        Rejection {
+            message: message.into(),
+        }
+    }
+
+    pub fn get_message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "request rejected: {}", self.message)
+    }
+}
+
+impl std::error::Error for Rejection {}
+
+/// An interceptor sees every request of a server before the service, e.g.
+/// to authenticate the caller or to audit the calls. The request is named
+/// after its variant in the ClientData of the service. Both hooks do
+/// nothing by default so that an interceptor only implements the one it
+/// needs.
+pub trait Interceptor: Send + Sync + RefUnwindSafe + 'static {
+    /// Called before the request is processed by the method of the service.
+    /// An error rejects the request which is then seen neither by the next
+    /// interceptors nor by the service.
+    fn before(&self, _request: &'static str, _ctx: &Context) -> Result<(), Rejection> {
+        Ok(())
+    }
+
+    /// Called with the outcome of a request the interceptor has let
+    /// through, even if a later interceptor rejected it.
+    fn after(&self, _request: &'static str, _ctx: &Context, _outcome: Outcome) {}
+}
+
+/// Ordered chain of interceptors wrapped around a processor. The requests
+/// go through the interceptors in the order they have been added, and the
+/// outcomes in the reverse order.
+#[derive(Clone, Default)]
+pub struct Chain {
+    interceptors: Vec<Arc<dyn Interceptor>>,
+}
+
+impl Chain {
+    pub fn new() -> Chain {
+        Chain::default()
+    }
+
+    /// Add the interceptor at the end of the chain.
+    pub fn with(mut self, interceptor: impl Interceptor) -> Self {
+        self.interceptors.push(Arc::new(interceptor));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.interceptors.is_empty()
+    }
+
+    /// Wrap the processor of a service so that its requests go through the
+    /// chain. The chain runs inside the processor, after the request has
+    /// been decoded and before the method is called.
+    pub fn wrap<Req: 'static, Rep: 'static>(self, p: Box<RequestProcessor<Req, Rep>>) -> Box<RequestProcessor<Req, Rep>> {
+        let chain = Arc::new(self);
+
+        Box::new(move |req, ctx| p(req, ctx.with_interceptors(&chain)))
+    }
+
+    /// Wrap the processor of an asynchronous service so that its requests
+    /// go through the chain.
+    #[cfg(feature = "async")]
+    pub fn wrap_async<Req: 'static, Rep: 'static>(
+        self,
+        p: Box<AsyncRequestProcessor<Req, Rep>>,
+    ) -> Box<AsyncRequestProcessor<Req, Rep>> {
+        let chain = Arc::new(self);
+
+        Box::new(move |req, ctx| p(req, ctx.with_interceptors(&chain)))
+    }
+
+    /// Return a chain with the interceptors of both, self first.
+    pub(crate) fn join(&self, other: &Chain) -> Chain {
+        let mut interceptors = self.interceptors.clone();
+        interceptors.extend(other.interceptors.iter().cloned());
+
+        Chain { interceptors }
+    }
+}
+
+impl fmt::Debug for Chain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Chain").field("len", &self.interceptors.len()).finish()
+    }
+}
+
+/// Run the interceptors of the context before the request. The guard
+/// returned when the request is let through reports its outcome.
+pub(crate) fn enter(request: &'static str, ctx: &Context) -> Result<Guard, Rejection> {
+    let chain = match ctx.get_interceptors() {
+        Some(chain) => chain,
+        None => return Ok(Guard::empty(request)),
+    };
+
+    for (i, interceptor) in chain.interceptors.iter().enumerate() {
+        if let Err(rejection) = interceptor.before(request, ctx) {
+            for interceptor in chain.interceptors[..i].iter().rev() {
+                interceptor.after(request, ctx, Outcome::Error);
+            }
+
+            return Err(rejection);
+        }
+    }
+
+    Ok(Guard {
+        request,
+        passed: Some((Arc::clone(chain), ctx.clone())),
+    })
+}
+
+/// Outcome of a request that went through the chain. A request that does
+/// not complete because the thread is unwinding is reported as a panic.
+pub(crate) struct Guard {
+    request: &'static str,
+    passed: Option<(Arc<Chain>, Context)>,
+}
+
+impl Guard {
+    fn empty(request: &'static str) -> Guard {
+        Guard { request, passed: None }
+    }
+
+    pub(crate) fn finish(mut self, outcome: Outcome) {
+        self.report(outcome);
+    }
+
+    fn report(&mut self, outcome: Outcome) {
+        if let Some((chain, ctx)) = self.passed.take() {
+            for interceptor in chain.interceptors.iter().rev() {
+                interceptor.after(self.request, &ctx, outcome);
+            }
+        }
+    }
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            self.report(Outcome::Panic);
+        }
+    }
+}
@@ -2,19 +2,131 @@ extern crate serde;
 
 pub mod executor;
 pub mod group;
+pub mod interceptor;
+/// Instrumentation of the requests used by the processors generated by the
+/// service macro around each call to an implementation.
+#[doc(hidden)]
+pub mod instrument;
+pub mod metrics;
 pub mod transport;
 
 pub use rpc_macro::service;
 
 use group::Address;
+use interceptor::{Chain, Interceptor};
+use metrics::Recorder;
+use std::collections::BTreeMap;
 use std::fmt;
-use std::sync::{mpsc, Arc};
-use std::thread::JoinHandle;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::{Builder, JoinHandle};
+use std::time::{Duration, Instant};
 use transport::{RequestProcessor, ServerTransport};
+#[cfg(feature = "async")]
+use transport::{AsyncRequestProcessor, AsyncServerTransport};
 
+/// Identity of a client authenticated with a certificate during the
+/// handshake of a secure transport.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PeerIdentity {
+    certificates: Vec<Vec<u8>>,
+    subject: String,
+    alt_names: Vec<String>,
+}
+
+impl PeerIdentity {
+    pub fn new(certificates: Vec<Vec<u8>>, subject: String, alt_names: Vec<String>) -> Self {
+        PeerIdentity {
+            certificates,
+            subject,
+            alt_names,
+        }
+    }
+
+    /// Get the certificate chain presented by the client, encoded in DER
+    /// with the client certificate first.
+    pub fn get_certificates(&self) -> &[Vec<u8>] {
+        &self.certificates
+    }
+
+    /// Get the distinguished name of the subject of the client certificate,
+    /// e.g. "CN=alice, O=example".
+    pub fn get_subject(&self) -> &str {
+        &self.subject
+    }
+
+    /// Get the DNS names, IP addresses, emails and URIs of the subject
+    /// alternative names of the client certificate.
+    pub fn get_alt_names(&self) -> &[String] {
+        &self.alt_names
+    }
+}
+
+/// Token telling the handler of a request that nobody waits for the reply
+/// anymore, because the client disconnected, its deadline elapsed or the
+/// server has given up on the request while shutting down. The clones share
+/// the same state.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        CancellationToken::default()
+    }
+
+    /// Create a token cancelled at the same time as this one, or when the
+    /// deadline elapses.
+    pub fn with_deadline(&self, deadline: Option<Instant>) -> Self {
+        CancellationToken {
+            cancelled: Arc::clone(&self.cancelled),
+            deadline,
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        if self.cancelled.load(Ordering::SeqCst) {
+            return true;
+        }
+
+        self.deadline.is_some_and(|deadline| deadline <= Instant::now())
+    }
+
+    pub fn get_deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+}
+
+/// String-keyed values sent alongside a request or a reply, e.g. an
+/// authentication token or a trace identifier.
+pub type Metadata = BTreeMap<String, String>;
+
+/// Options of a single call made by a client.
+#[derive(Clone, Debug, Default)]
+pub struct CallOptions {
+    /// Instant after which the client stops waiting for the reply.
+    pub deadline: Option<Instant>,
+    /// Metadata of the request, which takes precedence over the metadata
+    /// of the client for the same keys.
+    pub metadata: Metadata,
+}
+
+#[derive(Clone, Debug)]
 pub struct Context {
     in_addr: Address,
     out_addr: Address,
+    peer: Option<Arc<PeerIdentity>>,
+    cancel: CancellationToken,
+    metadata: Arc<Metadata>,
+    reply_metadata: Arc<Mutex<Metadata>>,
+    interceptors: Option<Arc<Chain>>,
+    metrics: Recorder,
 }
 
 impl Context {
@@ -22,82 +134,377 @@ impl Context {
         Context {
             in_addr,
             out_addr,
+            peer: None,
+            cancel: CancellationToken::new(),
+            metadata: Arc::new(Metadata::new()),
+            reply_metadata: Arc::new(Mutex::new(Metadata::new())),
+            interceptors: None,
+            metrics: Recorder::default(),
         }
     }
 
+    /// Attach the identity of the client verified by the transport.
+    pub fn with_peer(mut self, peer: Option<Arc<PeerIdentity>>) -> Self {
+        self.peer = peer;
+        self
+    }
+
+    /// Set the instant after which the client stops waiting for the reply.
+    pub fn with_deadline(mut self, deadline: Option<Instant>) -> Self {
+        self.cancel = self.cancel.with_deadline(deadline);
+        self
+    }
+
+    /// Attach the token cancelled by the transport when the client is gone.
+    /// The deadline of the context is kept.
+    pub fn with_cancellation(mut self, token: &CancellationToken) -> Self {
+        self.cancel = token.with_deadline(self.cancel.get_deadline());
+        self
+    }
+
+    /// Set the metadata sent by the client with the request. The metadata
+    /// of the reply starts empty.
+    pub fn with_metadata(mut self, metadata: Metadata) -> Self {
+        self.metadata = Arc::new(metadata);
+        self.reply_metadata = Arc::new(Mutex::new(Metadata::new()));
+        self
+    }
+
+    /// Set the interceptors the request goes through. They come after the
+    /// ones already set by an outer processor.
+    pub(crate) fn with_interceptors(mut self, chain: &Arc<Chain>) -> Self {
+        self.interceptors = match self.interceptors.take() {
+            Some(outer) => Some(Arc::new(outer.join(chain))),
+            None => Some(Arc::clone(chain)),
+        };
+        self
+    }
+
+    /// Set the sink of the measurements of the server.
+    pub(crate) fn with_metrics(mut self, metrics: Recorder) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
     pub fn get_in_addr(&self) -> &Address {
         &self.in_addr
     }
 
+    /// Get the identity of the client when it has been authenticated with
+    /// a certificate, or None otherwise.
+    pub fn get_peer_identity(&self) -> Option<&PeerIdentity> {
+        self.peer.as_deref()
+    }
+
     pub fn get_out_addr(&self) -> &Address {
         &self.out_addr
     }
+
+    /// Get the certificate chain presented by the client when the request
+    /// has been received over a secure transport, or None otherwise.
+    pub fn get_peer_certificates(&self) -> Option<&[Vec<u8>]> {
+        self.peer.as_ref().map(|peer| peer.get_certificates())
+    }
+
+    /// Get the instant after which the client stops waiting for the reply,
+    /// or None when it waits as long as needed. Work on the request can be
+    /// abandoned once it has elapsed as nobody will read the reply.
+    pub fn get_deadline(&self) -> Option<Instant> {
+        self.cancel.get_deadline()
+    }
+
+    /// Return true when nobody waits for the reply anymore so that a long
+    /// running handler can bail out early.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    /// Get the token of the request that can be handed over to the work
+    /// done on behalf of the handler.
+    pub fn get_cancellation_token(&self) -> &CancellationToken {
+        &self.cancel
+    }
+
+    /// Get the metadata sent by the client with the request.
+    pub fn get_metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Attach a value to the metadata sent back to the client with the
+    /// reply.
+    pub fn set_reply_metadata(&self, key: impl Into<String>, value: impl Into<String>) {
+        self.reply_metadata.lock().unwrap().insert(key.into(), value.into());
+    }
+
+    /// Take the metadata attached to the reply by the handler.
+    pub(crate) fn take_reply_metadata(&self) -> Metadata {
+        std::mem::take(&mut *self.reply_metadata.lock().unwrap())
+    }
+
+    pub(crate) fn get_interceptors(&self) -> Option<&Arc<Chain>> {
+        self.interceptors.as_ref()
+    }
+
+    pub(crate) fn get_metrics(&self) -> &Recorder {
+        &self.metrics
+    }
+}
+
+/// Time given to the requests in progress to complete when a server is
+/// dropped without being shut down.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+pub enum Error {
+    /// The transport failed to start or to stop, with the error of the
+    /// transport as the source.
+    TransportError(Box<dyn std::error::Error + Send + Sync>),
+    IoError(std::io::Error),
+}
+
+impl Error {
+    /// Get the kind of the IO error behind the failure, e.g. AddrInUse when
+    /// the address of the server is already taken, or None when the failure
+    /// is not one.
+    pub fn io_kind(&self) -> Option<std::io::ErrorKind> {
+        match self {
+            Error::IoError(err) => Some(err.kind()),
+            Error::TransportError(err) => {
+                if let Some(err) = err.downcast_ref::<transport::tcp::Error>() {
+                    return err.io_kind();
+                }
+
+                err.downcast_ref::<std::io::Error>().map(|err| err.kind())
+            }
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::TransportError(err) => Some(err.as_ref()),
+            Error::IoError(err) => Some(err),
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::IoError(err)
+    }
+}
+
+fn transport_error<E: std::error::Error + Send + Sync + 'static>(err: E) -> Error {
+    Error::TransportError(Box::new(err))
 }
 
 pub struct Server {
-    stop_tx: Option<mpsc::SyncSender<()>>,
-    addr: Option<Address>,
-    th: Option<JoinHandle<()>>,
+    interceptors: Chain,
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Server::new()
+    }
 }
 
 impl Server {
     pub fn new() -> Server {
         Server {
-            stop_tx: None,
-            addr: None,
-            th: None,
+            interceptors: Chain::new(),
         }
     }
 
+    /// Add an interceptor at the end of the chain that the requests of the
+    /// servers run afterwards go through.
+    pub fn with_interceptor(mut self, interceptor: impl Interceptor) -> Self {
+        self.interceptors = self.interceptors.with(interceptor);
+        self
+    }
+
+    /// Start the transport and serve the requests with the processor in a
+    /// thread of its own. The server runs until the handle is shut down or
+    /// dropped.
     pub fn run<Req: 'static, Rep: 'static>(
-        &mut self,
+        &self,
         p: Box<RequestProcessor<Req, Rep>>,
         t: impl ServerTransport<Req, Rep>,
-    ) {
-        self.addr = Some(t.get_addr());
+    ) -> Result<ServerHandle, Error> {
         let mut t = t;
 
+        let p = if self.interceptors.is_empty() {
+            p
+        } else {
+            self.interceptors.clone().wrap(p)
+        };
+
         let (tx, rx) = mpsc::sync_channel(1);
-        self.stop_tx = Some(tx);
 
-        t.connect().unwrap();
+        t.connect().map_err(transport_error)?;
 
-        println!("{} has started. Listening for incoming requests...", self);
+        // The address is known for sure only once the transport is bound.
+        let addr = t.get_addr();
 
-        self.th = Some(std::thread::spawn(move || {
-            let p = Arc::new(p);
+        let th = Builder::new()
+            .name(format!("server-{}", addr))
+            .spawn(move || {
+                let p = Arc::new(p);
 
-            loop {
-                match rx.try_recv() {
-                    Ok(_) => return, // received close announcement
-                    _ => (),
-                };
+                loop {
+                    if let Ok(deadline) = rx.try_recv() {
+                        // received close announcement
+                        return t.shutdown(deadline).map_err(transport_error);
+                    }
 
-                if let Err(e) = t.next(Arc::clone(&p)) {
-                    println!("Error: {:?}", e);
+                    if let Err(e) = t.next(Arc::clone(&p)) {
+                        tracing::error!(error = ?e, "failed to serve the next request");
+                    }
                 }
-            }
-        }));
+            })?;
+
+        let handle = ServerHandle {
+            stop_tx: tx,
+            addr,
+            th: Some(th),
+        };
+
+        tracing::info!(addr = %handle.addr, "server has started, listening for incoming requests");
+
+        Ok(handle)
+    }
+}
+
+/// Handle of a running server. The server is shut down when the handle is
+/// dropped.
+pub struct ServerHandle {
+    stop_tx: mpsc::SyncSender<Instant>,
+    addr: Address,
+    th: Option<JoinHandle<Result<usize, Error>>>,
+}
+
+impl ServerHandle {
+    /// Get the address the server is listening to, with the port chosen by
+    /// the OS when the transport has been bound to port 0.
+    pub fn get_addr(&self) -> &Address {
+        &self.addr
+    }
+
+    /// Stop accepting requests and let the ones in progress complete for
+    /// at most the given duration. It returns the number of requests that
+    /// have been aborted because they didn't complete in time.
+    pub fn shutdown(mut self, timeout: Duration) -> Result<usize, Error> {
+        self.stop(timeout)
+    }
+
+    fn stop(&mut self, timeout: Duration) -> Result<usize, Error> {
+        let th = match self.th.take() {
+            Some(th) => th,
+            None => return Ok(0),
+        };
+
+        // The thread only stops when asked to, unless it panicked which is
+        // reported by the join below.
+        self.stop_tx.send(Instant::now() + timeout).ok();
+
+        match th.join() {
+            Ok(res) => res,
+            Err(_) => Err(Error::TransportError("server thread panicked".into())),
+        }
     }
 }
 
-impl fmt::Display for Server {
+impl fmt::Display for ServerHandle {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Server[{}]", self.addr.as_ref().unwrap())
+        write!(f, "Server[{}]", self.addr)
     }
 }
 
-impl Drop for Server {
+impl Drop for ServerHandle {
     fn drop(&mut self) {
-        let tx = self.stop_tx.as_ref().unwrap();
+        // The server might have been shut down already.
+        if self.th.is_none() {
+            return;
+        }
+
+        match self.stop(SHUTDOWN_TIMEOUT) {
+            Ok(0) => (),
+            Ok(aborted) => tracing::warn!(addr = %self.addr, aborted, "server aborted requests"),
+            Err(e) => tracing::error!(addr = %self.addr, error = %e, "server failed to close"),
+        }
+
+        tracing::info!(addr = %self.addr, "server has been closed");
+    }
+}
+
+/// Serve the requests of an asynchronous service with the given transport.
+/// The transport is started before the future resolves to the handle of the
+/// server, whose requests are then served by a task of the runtime.
+#[cfg(feature = "async")]
+pub async fn serve<Req, Rep, T>(
+    p: Box<AsyncRequestProcessor<Req, Rep>>,
+    t: T,
+) -> Result<AsyncServerHandle, T::Error>
+where
+    Req: 'static,
+    Rep: 'static,
+    T: AsyncServerTransport<Req, Rep>,
+{
+    let mut t = t;
+
+    t.connect().await?;
+
+    // The address is known for sure only once the transport is bound.
+    let addr = t.get_addr();
+    tracing::info!(addr = %addr, "server has started, listening for incoming requests");
 
-        if let Err(ref e) = tx.send(()) {
-            println!("Close error: {}", e);
+    let p = Arc::new(p);
+
+    let task = tokio::spawn(async move {
+        loop {
+            if let Err(e) = t.next(Arc::clone(&p)).await {
+                tracing::error!(error = ?e, "failed to serve the next request");
+            }
         }
+    });
+
+    Ok(AsyncServerHandle { addr, task })
+}
 
-        // Make sure the main thread is stopped.
-        self.th.take().unwrap().join().unwrap();
+/// Handle of a server running in an async runtime. Like the tasks of the
+/// runtime, the server keeps running when the handle is dropped.
+#[cfg(feature = "async")]
+pub struct AsyncServerHandle {
+    addr: Address,
+    task: tokio::task::JoinHandle<()>,
+}
+
+#[cfg(feature = "async")]
+impl AsyncServerHandle {
+    /// Get the address the server is listening to, with the port chosen by
+    /// the OS when the transport has been bound to port 0.
+    pub fn get_addr(&self) -> &Address {
+        &self.addr
+    }
+
+    /// Stop accepting connections. The connections already accepted are
+    /// served until their clients close them.
+    pub fn shutdown(self) {
+        self.task.abort();
 
-        println!("{} has been closed.", self);
+        tracing::info!(addr = %self.addr, "server has been closed");
+    }
+}
+
+#[cfg(feature = "async")]
+impl fmt::Display for AsyncServerHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Server[{}]", self.addr)
     }
 }
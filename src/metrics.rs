@@ -0,0 +1,252 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::panic::RefUnwindSafe;
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::Duration;
+
+/// Upper bounds in seconds of the buckets of the latency histograms.
+const BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Outcome of a request processed by a service.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Outcome {
+    Success,
+    Error,
+    Panic,
+}
+
+/// A sink receives the measurements of the servers of the process, or of
+/// a single server when it is given to its transport. Every measurement is
+/// ignored by default so that a sink only implements the ones it is
+/// interested in.
+pub trait MetricsSink: Send + Sync + RefUnwindSafe {
+    /// Record a request processed by the method of a service.
+    fn record_request(&self, _service: &str, _method: &str, _outcome: Outcome, _elapsed: Duration) {}
+
+    /// Record a connection accepted by the server at the given address.
+    fn connection_accepted(&self, _server: &str) {}
+
+    /// Record the number of bytes received by the server at the given
+    /// address.
+    fn bytes_received(&self, _server: &str, _n: usize) {}
+
+    /// Record the number of bytes sent by the server at the given address.
+    fn bytes_sent(&self, _server: &str, _n: usize) {}
+
+    /// Record the number of requests of the server at the given address
+    /// that are queued or running in its executor.
+    fn queue_depth(&self, _server: &str, _depth: usize) {}
+}
+
+fn global() -> &'static RwLock<Option<Arc<dyn MetricsSink>>> {
+    static SINK: OnceLock<RwLock<Option<Arc<dyn MetricsSink>>>> = OnceLock::new();
+
+    SINK.get_or_init(|| RwLock::new(None))
+}
+
+/// Set the sink receiving the measurements of the servers of the process.
+pub fn set_sink(sink: Arc<dyn MetricsSink>) {
+    *global().write().unwrap() = Some(sink);
+}
+
+/// Pass the measurement to the sink if any is set.
+pub(crate) fn record<F: FnOnce(&dyn MetricsSink)>(f: F) {
+    if let Some(sink) = global().read().unwrap().as_ref() {
+        f(sink.as_ref());
+    }
+}
+
+/// Sink of the measurements of a server, which is the sink of the process
+/// unless the server has been given one of its own.
+#[derive(Clone, Default)]
+pub(crate) struct Recorder {
+    sink: Option<Arc<dyn MetricsSink>>,
+}
+
+impl Recorder {
+    pub(crate) fn new(sink: Arc<dyn MetricsSink>) -> Recorder {
+        Recorder { sink: Some(sink) }
+    }
+
+    /// Pass the measurement to the sink of the server or of the process.
+    pub(crate) fn record<F: FnOnce(&dyn MetricsSink)>(&self, f: F) {
+        match self.sink.as_ref() {
+            Some(sink) => f(sink.as_ref()),
+            None => record(f),
+        }
+    }
+}
+
+impl fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Recorder").field("own_sink", &self.sink.is_some()).finish()
+    }
+}
+
+/// Escape the value of a label as the text format requires, as the address
+/// of a Unix domain socket can contain any character.
+fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+/// Name, help and value of a counter of the methods.
+type MethodCounter = (&'static str, &'static str, fn(&MethodStats) -> u64);
+
+#[derive(Default)]
+struct MethodStats {
+    calls: u64,
+    errors: u64,
+    panics: u64,
+    buckets: [u64; BUCKETS.len()],
+    sum: f64,
+}
+
+/// Measurements of the transport of a server.
+#[derive(Default)]
+struct ServerStats {
+    connections: u64,
+    bytes_in: u64,
+    bytes_out: u64,
+    /// Only the servers running an executor report the depth of its queue.
+    queue_depth: Option<u64>,
+}
+
+/// Name, help and value of a counter of the servers.
+type ServerCounter = (&'static str, &'static str, fn(&ServerStats) -> u64);
+
+/// Sink keeping the measurements in memory so that they can be exposed in
+/// the text format of Prometheus.
+#[derive(Default)]
+pub struct Registry {
+    methods: Mutex<BTreeMap<(String, String), MethodStats>>,
+    servers: Mutex<BTreeMap<String, ServerStats>>,
+}
+
+impl Registry {
+    pub fn new() -> Registry {
+        Registry::default()
+    }
+
+    /// Update the measurements of the server at the given address.
+    fn server<F: FnOnce(&mut ServerStats)>(&self, server: &str, f: F) {
+        let mut servers = self.servers.lock().unwrap();
+
+        match servers.get_mut(server) {
+            Some(stats) => f(stats),
+            None => f(servers.entry(server.to_string()).or_default()),
+        }
+    }
+
+    /// Render the measurements in the text exposition format of Prometheus.
+    pub fn render(&self) -> String {
+        let methods = self.methods.lock().unwrap();
+        let mut out = String::new();
+
+        let counters: [MethodCounter; 3] = [
+            ("rpc_requests_total", "Number of requests processed.", |s| s.calls),
+            ("rpc_request_errors_total", "Number of requests that returned an error.", |s| s.errors),
+            ("rpc_request_panics_total", "Number of requests that panicked.", |s| s.panics),
+        ];
+
+        for (name, help, value) in counters.iter() {
+            writeln!(out, "# HELP {} {}", name, help).unwrap();
+            writeln!(out, "# TYPE {} counter", name).unwrap();
+            for ((service, method), stats) in methods.iter() {
+                let labels = format!("service=\"{}\",method=\"{}\"", escape(service), escape(method));
+                writeln!(out, "{}{{{}}} {}", name, labels, value(stats)).unwrap();
+            }
+        }
+
+        let name = "rpc_request_duration_seconds";
+        writeln!(out, "# HELP {} Latency of the requests.", name).unwrap();
+        writeln!(out, "# TYPE {} histogram", name).unwrap();
+        for ((service, method), stats) in methods.iter() {
+            let labels = format!("service=\"{}\",method=\"{}\"", escape(service), escape(method));
+            for (le, count) in BUCKETS.iter().zip(stats.buckets.iter()) {
+                writeln!(out, "{}_bucket{{{},le=\"{}\"}} {}", name, labels, le, count).unwrap();
+            }
+            writeln!(out, "{}_bucket{{{},le=\"+Inf\"}} {}", name, labels, stats.calls).unwrap();
+            writeln!(out, "{}_sum{{{}}} {}", name, labels, stats.sum).unwrap();
+            writeln!(out, "{}_count{{{}}} {}", name, labels, stats.calls).unwrap();
+        }
+
+        let servers = self.servers.lock().unwrap();
+
+        let counters: [ServerCounter; 3] = [
+            ("rpc_connections_accepted_total", "Number of connections accepted.", |s| s.connections),
+            ("rpc_received_bytes_total", "Number of bytes received.", |s| s.bytes_in),
+            ("rpc_sent_bytes_total", "Number of bytes sent.", |s| s.bytes_out),
+        ];
+
+        for (name, help, value) in counters.iter() {
+            writeln!(out, "# HELP {} {}", name, help).unwrap();
+            writeln!(out, "# TYPE {} counter", name).unwrap();
+            for (server, stats) in servers.iter() {
+                writeln!(out, "{}{{server=\"{}\"}} {}", name, escape(server), value(stats)).unwrap();
+            }
+        }
+
+        let name = "rpc_queue_depth";
+        writeln!(out, "# HELP {} Number of requests queued or running in the executor.", name).unwrap();
+        writeln!(out, "# TYPE {} gauge", name).unwrap();
+        for (server, stats) in servers.iter() {
+            if let Some(depth) = stats.queue_depth {
+                writeln!(out, "{}{{server=\"{}\"}} {}", name, escape(server), depth).unwrap();
+            }
+        }
+
+        out
+    }
+}
+
+impl MetricsSink for Registry {
+    /// Record the request under the names of its service and of its method,
+    /// as several services can have methods of the same name.
+    fn record_request(&self, service: &str, method: &str, outcome: Outcome, elapsed: Duration) {
+        let mut methods = self.methods.lock().unwrap();
+        let stats = methods.entry((service.to_string(), method.to_string())).or_default();
+
+        stats.calls += 1;
+        match outcome {
+            Outcome::Success => (),
+            Outcome::Error => stats.errors += 1,
+            Outcome::Panic => stats.panics += 1,
+        }
+
+        // Buckets are cumulative.
+        let secs = elapsed.as_secs_f64();
+        for (le, count) in BUCKETS.iter().zip(stats.buckets.iter_mut()) {
+            if secs <= *le {
+                *count += 1;
+            }
+        }
+        stats.sum += secs;
+    }
+
+    fn connection_accepted(&self, server: &str) {
+        self.server(server, |s| s.connections += 1);
+    }
+
+    fn bytes_received(&self, server: &str, n: usize) {
+        self.server(server, |s| s.bytes_in += n as u64);
+    }
+
+    fn bytes_sent(&self, server: &str, n: usize) {
+        self.server(server, |s| s.bytes_out += n as u64);
+    }
+
+    fn queue_depth(&self, server: &str, depth: usize) {
+        self.server(server, |s| s.queue_depth = Some(depth as u64));
+    }
+}
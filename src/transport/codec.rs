@@ -0,0 +1,152 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::fmt;
+use std::io;
+
+/// Error returned when a message can't be encoded or decoded. The position
+/// of the error in the payload is known for the text encodings.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CodecError {
+    message: String,
+    position: Option<(usize, usize)>,
+}
+
+impl CodecError {
+    pub fn new(message: String) -> CodecError {
+        CodecError {
+            message,
+            position: None,
+        }
+    }
+
+    /// Create an error that happened at the given line and column of the
+    /// payload.
+    pub fn at(message: String, line: usize, column: usize) -> CodecError {
+        CodecError {
+            message,
+            position: Some((line, column)),
+        }
+    }
+
+    pub fn get_message(&self) -> &str {
+        &self.message
+    }
+
+    /// Get the line of the payload where the error happened, starting at 1,
+    /// or None when the position is unknown.
+    pub fn get_line(&self) -> Option<usize> {
+        self.position.map(|(line, _)| line)
+    }
+
+    /// Get the column of the payload where the error happened, starting at
+    /// 1, or None when the position is unknown.
+    pub fn get_column(&self) -> Option<usize> {
+        self.position.map(|(_, column)| column)
+    }
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for CodecError {}
+
+impl From<CodecError> for io::Error {
+    fn from(err: CodecError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+impl From<serde_json::Error> for CodecError {
+    fn from(err: serde_json::Error) -> Self {
+        // The position is unknown when the line is zero, e.g. for errors
+        // raised while encoding.
+        match err.line() {
+            0 => CodecError::new(err.to_string()),
+            line => CodecError::at(err.to_string(), line, err.column()),
+        }
+    }
+}
+
+/// A codec defines how the messages are turned into bytes before being
+/// written by a transport, and back into messages after being read.
+pub trait Codec: Send + Sync + 'static {
+    /// Name of the encoding announced to the peer when a connection is
+    /// opened.
+    const NAME: &'static str;
+
+    /// Serialize the message into bytes.
+    fn encode<T: Serialize>(msg: &T) -> Result<Vec<u8>, CodecError>;
+    /// Deserialize the bytes into a message.
+    fn decode<T: DeserializeOwned>(buf: &[u8]) -> Result<T, CodecError>;
+}
+
+/// Codec using JSON which is the default of the transports.
+pub struct JsonCodec;
+
+impl Codec for JsonCodec {
+    const NAME: &'static str = "json";
+
+    fn encode<T: Serialize>(msg: &T) -> Result<Vec<u8>, CodecError> {
+        Ok(serde_json::to_vec(msg)?)
+    }
+
+    fn decode<T: DeserializeOwned>(buf: &[u8]) -> Result<T, CodecError> {
+        Ok(serde_json::from_slice(buf)?)
+    }
+}
+
+/// Codec using the compact binary format of bincode.
+#[cfg(feature = "bincode")]
+pub struct BincodeCodec;
+
+#[cfg(feature = "bincode")]
+impl Codec for BincodeCodec {
+    const NAME: &'static str = "bincode";
+
+    fn encode<T: Serialize>(msg: &T) -> Result<Vec<u8>, CodecError> {
+        bincode::serialize(msg).map_err(|e| CodecError::new(e.to_string()))
+    }
+
+    fn decode<T: DeserializeOwned>(buf: &[u8]) -> Result<T, CodecError> {
+        bincode::deserialize(buf).map_err(|e| CodecError::new(e.to_string()))
+    }
+}
+
+/// Codec using the MessagePack binary format.
+#[cfg(feature = "msgpack")]
+pub struct MsgPackCodec;
+
+#[cfg(feature = "msgpack")]
+impl Codec for MsgPackCodec {
+    const NAME: &'static str = "msgpack";
+
+    fn encode<T: Serialize>(msg: &T) -> Result<Vec<u8>, CodecError> {
+        rmp_serde::to_vec(msg).map_err(|e| CodecError::new(e.to_string()))
+    }
+
+    fn decode<T: DeserializeOwned>(buf: &[u8]) -> Result<T, CodecError> {
+        rmp_serde::from_slice(buf).map_err(|e| CodecError::new(e.to_string()))
+    }
+}
+
+/// Serialize the message with the codec if the encoding negotiated with the
+/// peer is its own, otherwise with the JSON fallback.
+pub(crate) fn encode_with<C: Codec, T: Serialize>(encoding: &str, msg: &T) -> Result<Vec<u8>, CodecError> {
+    if encoding == C::NAME {
+        C::encode(msg)
+    } else {
+        JsonCodec::encode(msg)
+    }
+}
+
+/// Deserialize the bytes with the codec if the encoding negotiated with the
+/// peer is its own, otherwise with the JSON fallback.
+pub(crate) fn decode_with<C: Codec, T: DeserializeOwned>(encoding: &str, buf: &[u8]) -> Result<T, CodecError> {
+    if encoding == C::NAME {
+        C::decode(buf)
+    } else {
+        JsonCodec::decode(buf)
+    }
+}
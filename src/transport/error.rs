@@ -0,0 +1,95 @@
+use super::codec::CodecError;
+use super::local;
+use super::stream::{self, IoError};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+
+/// Failure of a call seen by the generated clients, whatever the transport
+/// used. The error of the service converts from it so that callers can
+/// match on why the call failed.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum CallError {
+    /// The server failed to process the request because the implementation
+    /// panicked with the given message.
+    Internal(String),
+    /// The server rejected the request because its queue is full.
+    ServerBusy,
+    /// The message is larger than the maximum size accepted by the peer
+    /// reading it.
+    MessageTooLarge,
+    /// The reply didn't come before the deadline or the timeout of the call.
+    TimedOut,
+    /// The server can't understand the request for the given reason.
+    BadRequest(String),
+    /// An interceptor of the server refused the request for the reason.
+    Rejected(String),
+    /// The connection to the server failed, with the kind telling whether
+    /// it was refused, reset or timed out among others.
+    Io(IoError),
+    /// The request or the reply can't be encoded or decoded.
+    Codec(CodecError),
+    /// A stream transport failed to carry the request or the reply for
+    /// another reason, e.g. the server doesn't speak the same protocol.
+    Transport(stream::Error),
+    /// The local transport failed to carry the request or the reply.
+    Local(local::Error),
+    /// A transport outside of the crate failed with the given message.
+    Other(String),
+}
+
+impl fmt::Display for CallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl Error for CallError {}
+
+impl CallError {
+    /// Convert the error of any client transport. The errors of the
+    /// transports of the crate keep their details and the others are only
+    /// known by their message.
+    pub fn from_transport<E: Error + 'static>(err: E) -> CallError {
+        let err: Box<dyn Error> = Box::new(err);
+
+        let err = match err.downcast::<stream::Error>() {
+            Ok(err) => return CallError::from(*err),
+            Err(err) => err,
+        };
+        let err = match err.downcast::<local::Error>() {
+            Ok(err) => return CallError::from(*err),
+            Err(err) => err,
+        };
+
+        match err.downcast::<CallError>() {
+            Ok(err) => *err,
+            Err(err) => CallError::Other(err.to_string()),
+        }
+    }
+}
+
+impl From<stream::Error> for CallError {
+    fn from(err: stream::Error) -> Self {
+        match err {
+            stream::Error::Internal(msg) => CallError::Internal(msg),
+            stream::Error::ServerBusy => CallError::ServerBusy,
+            stream::Error::MessageTooLarge => CallError::MessageTooLarge,
+            stream::Error::BadRequest(reason) => CallError::BadRequest(reason),
+            err if err.is_timeout() => CallError::TimedOut,
+            stream::Error::IoError(err) => CallError::Io(err),
+            stream::Error::SerdeError(err) => CallError::Codec(err),
+            err => CallError::Transport(err),
+        }
+    }
+}
+
+impl From<local::Error> for CallError {
+    fn from(err: local::Error) -> Self {
+        match err {
+            local::Error::Internal(msg) => CallError::Internal(msg),
+            local::Error::TimedOut => CallError::TimedOut,
+            err => CallError::Local(err),
+        }
+    }
+}
@@ -0,0 +1,306 @@
+use super::super::{
+    executor::{Executor, ThreadPool},
+    group::Address,
+    CallOptions,
+    CancellationToken,
+    Context,
+    Metadata,
+    RequestProcessor,
+};
+use super::{panic_message, ClientTransport, ServerTransport};
+use super::stream::CancelOnDrop;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::collections::HashMap;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+const WAIT_TIMEOUT: Duration = Duration::from_millis(100);
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum Error {
+    /// The address is not a local one.
+    NotLocalAddress,
+    /// A server is already registered under the name.
+    AddrInUse,
+    /// No server is registered under the name.
+    NotFound,
+    /// The server registered under the name serves other messages.
+    TypeMismatch,
+    /// The server stopped before replying.
+    Closed,
+    /// The deadline of the request elapsed before the reply came.
+    TimedOut,
+    /// The server failed to process the request because the implementation
+    /// panicked with the given message.
+    Internal(String),
+    NotRunning,
+    IoError(String),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::IoError(err.to_string())
+    }
+}
+
+/// A request sent to a local server with the channel to use to reply. The
+/// token is cancelled by the client when it stops waiting for the reply.
+struct Call<Req, Rep> {
+    msg: Req,
+    in_addr: Address,
+    deadline: Option<Instant>,
+    metadata: Metadata,
+    cancel: CancellationToken,
+    reply: mpsc::Sender<Result<(Rep, Metadata), String>>,
+}
+
+/// Registry of the local servers of the process. Each entry holds the
+/// sender of the server channel which is downcast by the clients.
+fn registry() -> &'static Mutex<HashMap<String, Box<dyn Any + Send>>> {
+    static REGISTRY: OnceLock<Mutex<HashMap<String, Box<dyn Any + Send>>>> = OnceLock::new();
+
+    REGISTRY.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn local_name(addr: &Address) -> Result<&str, Error> {
+    match addr {
+        Address::Local(name) => Ok(name),
+        _ => Err(Error::NotLocalAddress),
+    }
+}
+
+/// ServerTransport implementation for servers living in the same process
+/// as their clients. Messages are passed through channels without being
+/// serialized.
+pub struct LocalServerTransport<Req, Rep> {
+    addr: Address,
+    rx: Option<mpsc::Receiver<Call<Req, Rep>>>,
+    pool: ThreadPool,
+}
+
+impl<Req, Rep> LocalServerTransport<Req, Rep> {
+    /// Create a transport that will register the server under the name
+    /// of the local address.
+    pub fn new(addr: Address) -> LocalServerTransport<Req, Rep> {
+        LocalServerTransport {
+            addr,
+            rx: None,
+            pool: ThreadPool::new(4),
+        }
+    }
+}
+
+impl<Req, Rep> ServerTransport<Req, Rep> for LocalServerTransport<Req, Rep>
+where
+    Req: Send + 'static,
+    Rep: Send + 'static,
+{
+    type Error = Error;
+
+    /// Get the local address of the server.
+    fn get_addr(&self) -> Address {
+        self.addr.clone()
+    }
+
+    /// Register the server under its name so that clients can find it.
+    fn connect(&mut self) -> Result<(), Error> {
+        let name = local_name(&self.addr)?;
+        let mut registry = registry().lock().unwrap();
+
+        if registry.contains_key(name) {
+            return Err(Error::AddrInUse);
+        }
+
+        let (tx, rx) = mpsc::channel::<Call<Req, Rep>>();
+        registry.insert(name.to_string(), Box::new(tx));
+
+        self.rx = Some(rx);
+
+        Ok(())
+    }
+
+    /// Wait for the next request and process it with the pool of workers.
+    fn next(&mut self, f: Arc<Box<RequestProcessor<Req, Rep>>>) -> Result<(), Error> {
+        let rx = match self.rx.as_ref() {
+            Some(rx) => rx,
+            None => return Err(Error::NotRunning),
+        };
+
+        let call = match rx.recv_timeout(WAIT_TIMEOUT) {
+            Ok(call) => call,
+            // As the next is looped, we simply return to wait again.
+            Err(_) => return Ok(()),
+        };
+
+        let out_addr = self.addr.clone();
+        // The request is only moved into the processor.
+        let call = AssertUnwindSafe(call);
+
+        self.pool.execute(Box::new(move || -> io::Result<()> {
+            let Call { msg, in_addr, deadline, metadata, cancel, reply } = call.0;
+            let msg = AssertUnwindSafe(msg);
+            let ctx = Context::new(in_addr, out_addr)
+                .with_cancellation(&cancel)
+                .with_deadline(deadline)
+                .with_metadata(metadata);
+
+            // The client stopped waiting while the request was queued.
+            if ctx.is_cancelled() {
+                tracing::debug!(peer = %ctx.get_in_addr(), "request cancelled before being processed");
+                return Ok(());
+            }
+
+            let reply_ctx = ctx.clone();
+
+            let res = match panic::catch_unwind(move || f(msg.0, ctx)) {
+                Ok(rep) => Ok((rep, reply_ctx.take_reply_metadata())),
+                Err(e) => {
+                    let msg = panic_message(&*e);
+                    tracing::error!(panic = %msg, "request panicked");
+
+                    Err(msg)
+                }
+            };
+
+            // The client might have given up already.
+            reply.send(res).ok();
+            Ok(())
+        }))?;
+
+        Ok(())
+    }
+
+    /// Unregister the server so that clients can't find it anymore, then
+    /// let the requests in progress complete until the deadline. Requests
+    /// that have not been picked yet are aborted.
+    fn shutdown(&mut self, deadline: Instant) -> Result<usize, Error> {
+        let queued = match self.close() {
+            Some(rx) => rx.try_iter().count(),
+            None => 0,
+        };
+
+        Ok(queued + self.pool.shutdown(deadline))
+    }
+}
+
+impl<Req, Rep> LocalServerTransport<Req, Rep> {
+    fn close(&mut self) -> Option<mpsc::Receiver<Call<Req, Rep>>> {
+        let rx = self.rx.take()?;
+
+        if let Ok(name) = local_name(&self.addr) {
+            registry().lock().unwrap().remove(name);
+        }
+
+        Some(rx)
+    }
+}
+
+impl<Req, Rep> Drop for LocalServerTransport<Req, Rep> {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// ClientTransport implementation that talks to a server registered in
+/// the same process.
+pub struct LocalClientTransport {
+    addr: Address,
+    in_addr: Address,
+}
+
+impl LocalClientTransport {
+    /// Create a client transport that will send the requests to the
+    /// server registered under the name of the local address.
+    pub fn new(addr: Address) -> LocalClientTransport {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+
+        LocalClientTransport {
+            addr,
+            in_addr: Address::Local(format!("local-client-{}", id)),
+        }
+    }
+}
+
+impl<Req, Rep> ClientTransport<Req, Rep> for LocalClientTransport
+where
+    Req: Send + 'static,
+    Rep: Send + 'static,
+{
+    type Error = Error;
+
+    /// Send the message to the local server and wait for the reply.
+    fn send(&self, msg: Req) -> Result<Rep, Error> {
+        self.send_with_deadline(msg, None)
+    }
+
+    /// Send the message to the local server and wait for the reply until
+    /// the deadline.
+    fn send_with_deadline(&self, msg: Req, deadline: Option<Instant>) -> Result<Rep, Error> {
+        let opts = CallOptions {
+            deadline,
+            ..CallOptions::default()
+        };
+
+        self.send_with_options(msg, &opts).map(|(rep, _)| rep)
+    }
+
+    /// Send the message with the metadata of the options to the local
+    /// server and wait for the reply and its metadata until the deadline.
+    fn send_with_options(&self, msg: Req, opts: &CallOptions) -> Result<(Rep, Metadata), Error> {
+        let deadline = opts.deadline;
+        let name = local_name(&self.addr)?;
+
+        let tx = match registry().lock().unwrap().get(name) {
+            Some(tx) => match tx.downcast_ref::<mpsc::Sender<Call<Req, Rep>>>() {
+                Some(tx) => tx.clone(),
+                None => return Err(Error::TypeMismatch),
+            },
+            None => return Err(Error::NotFound),
+        };
+
+        // The request is cancelled unless the reply comes, e.g. when the
+        // deadline elapses or the caller unwinds.
+        let mut cancel = CancelOnDrop::new();
+        let (reply, rx) = mpsc::channel();
+        let call = Call {
+            msg,
+            in_addr: self.in_addr.clone(),
+            deadline,
+            metadata: opts.metadata.clone(),
+            cancel: cancel.token().clone(),
+            reply,
+        };
+
+        if tx.send(call).is_err() {
+            return Err(Error::Closed);
+        }
+
+        let res = match deadline {
+            Some(deadline) => rx.recv_timeout(deadline.saturating_duration_since(Instant::now())),
+            None => rx.recv().map_err(mpsc::RecvTimeoutError::from),
+        };
+
+        match res {
+            Ok(res) => {
+                cancel.disarm();
+                res.map_err(Error::Internal)
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => Err(Error::TimedOut),
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(Error::Closed),
+        }
+    }
+}
@@ -1,16 +1,45 @@
+pub mod codec;
+mod error;
+pub mod local;
+mod stream;
 pub mod tcp;
+#[cfg(feature = "tls")]
+pub mod tls;
+#[cfg(unix)]
+pub mod unix;
+
+pub use self::codec::Codec;
+pub use self::error::CallError;
 
 use super::group::Address;
+use super::{CallOptions, Context, Metadata};
+use std::any::Any;
 use std::sync::Arc;
 use std::panic::RefUnwindSafe;
+use std::time::Instant;
+#[cfg(feature = "async")]
+use std::{future::Future, pin::Pin};
 
 /// Processor created by services that will be used by the server
 /// to process the requests sent by the clients.
-pub type RequestProcessor<Req, Rep> = dyn Fn(Req, Address, Address) -> Rep + Send + Sync + RefUnwindSafe;
+pub type RequestProcessor<Req, Rep> = dyn Fn(Req, Context) -> Rep + Send + Sync + RefUnwindSafe;
+
+/// Extract the message given to the panic! macro from the payload of a
+/// panic caught while processing a request.
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        return String::from(*msg);
+    }
+
+    match payload.downcast_ref::<String>() {
+        Some(msg) => msg.clone(),
+        None => String::from("unknown panic"),
+    }
+}
 
 /// A server transport defines how the server will receive requests.
 pub trait ServerTransport<Req, Rep>: Send + 'static {
-    type Error: std::fmt::Debug;
+    type Error: std::error::Error + Send + Sync + 'static;
 
     /// Get the address that identify the server.
     fn get_addr(&self) -> Address;
@@ -19,6 +48,10 @@ pub trait ServerTransport<Req, Rep>: Send + 'static {
     /// Serve the next incoming connection using the processor
     /// to generate the response.
     fn next(&mut self, f: Arc<Box<RequestProcessor<Req, Rep>>>) -> Result<(), Self::Error>;
+    /// Stop accepting requests and wait for the ones in progress to
+    /// complete until the deadline. It returns the number of requests
+    /// that have been aborted.
+    fn shutdown(&mut self, deadline: Instant) -> Result<usize, Self::Error>;
 }
 
 // A client transport defines how the client will talk to the server.
@@ -28,4 +61,77 @@ pub trait ClientTransport<Req, Rep> {
     /// Create a connection that can be used to send messages to
     /// a server.
     fn send(&self, msg: Req) -> Result<Rep, Self::Error>;
+
+    /// Send the message and stop waiting for the reply when the deadline
+    /// elapses. The deadline is given to the server so that it can abandon
+    /// the request. Transports without deadlines simply send the message.
+    fn send_with_deadline(&self, msg: Req, deadline: Option<Instant>) -> Result<Rep, Self::Error> {
+        let _ = deadline;
+        self.send(msg)
+    }
+
+    /// Send the message with the metadata and the deadline of the options
+    /// and return the reply with the metadata attached by the server.
+    /// Transports without metadata only honor the deadline.
+    fn send_with_options(&self, msg: Req, opts: &CallOptions) -> Result<(Rep, Metadata), Self::Error> {
+        self.send_with_deadline(msg, opts.deadline).map(|rep| (rep, Metadata::new()))
+    }
+}
+
+/// Processor created by asynchronous services. The future resolves to the
+/// response of the request.
+#[cfg(feature = "async")]
+pub type AsyncRequestProcessor<Req, Rep> =
+    dyn Fn(Req, Context) -> Pin<Box<dyn Future<Output = Rep> + Send>> + Send + Sync;
+
+/// An asynchronous server transport defines how the server will receive
+/// requests inside an async runtime.
+#[cfg(feature = "async")]
+pub trait AsyncServerTransport<Req, Rep>: Send + 'static {
+    type Error: std::fmt::Debug;
+
+    /// Get the address that identify the server.
+    fn get_addr(&self) -> Address;
+    /// Start to listen for incoming requests.
+    fn connect(&mut self) -> impl Future<Output = Result<(), Self::Error>> + Send;
+    /// Serve the next incoming connection using the processor
+    /// to generate the response.
+    fn next(
+        &mut self,
+        f: Arc<Box<AsyncRequestProcessor<Req, Rep>>>,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send;
+}
+
+/// An asynchronous client transport defines how the client will talk to
+/// the server inside an async runtime.
+#[cfg(feature = "async")]
+pub trait AsyncClientTransport<Req, Rep> {
+    type Error: std::error::Error;
+
+    /// Send a message to the server and resolve to the reply.
+    fn send(&self, msg: Req) -> impl Future<Output = Result<Rep, Self::Error>> + Send;
+
+    /// Send the message and stop waiting for the reply when the deadline
+    /// elapses. The deadline is given to the server so that it can abandon
+    /// the request. Transports without deadlines simply send the message.
+    fn send_with_deadline(
+        &self,
+        msg: Req,
+        deadline: Option<Instant>,
+    ) -> impl Future<Output = Result<Rep, Self::Error>> + Send {
+        let _ = deadline;
+        self.send(msg)
+    }
+
+    /// Send the message with the metadata and the deadline of the options
+    /// and resolve to the reply with the metadata attached by the server.
+    /// Transports without metadata only honor the deadline.
+    fn send_with_options(
+        &self,
+        msg: Req,
+        opts: &CallOptions,
+    ) -> impl Future<Output = Result<(Rep, Metadata), Self::Error>> + Send {
+        let fut = self.send_with_deadline(msg, opts.deadline);
+        async move { fut.await.map(|rep| (rep, Metadata::new())) }
+    }
 }
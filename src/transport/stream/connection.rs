@@ -0,0 +1,137 @@
+use super::handshake::{self, Hello};
+use super::pending::{Pending, ReplyResult};
+use super::pool::PoolConfig;
+use super::{closed_error, deadline_error, read_frame, read_header, read_payload, skip_payload, write_frame, Error, Stream};
+use std::net::Shutdown;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::Builder;
+use std::time::Instant;
+
+/// Connection to a server that can be shared by several threads. Each
+/// request is tagged with an identifier so that the replies can be sent
+/// back to the right caller whatever the order they arrive in.
+pub(crate) struct Connection<S: Stream> {
+    encoding: String,
+    writer: Mutex<S>,
+    pending: Arc<Pending<mpsc::Sender<ReplyResult>>>,
+    last_used: Mutex<Instant>,
+}
+
+impl<S: Stream> Connection<S> {
+    /// Create a connection over the stream by negotiating the version and
+    /// the encoding with the server, and start the thread that reads the
+    /// replies. Replies larger than the maximum size of the configuration
+    /// are discarded.
+    pub(crate) fn new(mut stream: S, encodings: Vec<String>, config: &PoolConfig) -> Result<Connection<S>, Error> {
+        let max_size = config.max_message_size;
+        stream.set_write_timeout(config.write_timeout)?;
+        // The server is expected to answer the handshake as fast as it
+        // accepts the connection.
+        stream.set_read_timeout(config.connect_timeout)?;
+
+        let hello = Hello::new(encodings);
+        write_frame(&mut stream, 0, &hello.to_bytes()?)?;
+
+        let encoding = handshake::negotiated(read_frame(&mut stream, max_size)?, &hello)?;
+
+        // The replies are waited for by the callers with their own deadline.
+        stream.set_read_timeout(None)?;
+
+        let mut reader = stream.try_clone()?;
+        let pending = Arc::new(Pending::new());
+
+        let p = Arc::clone(&pending);
+        Builder::new()
+            .name(String::from("client-connection"))
+            .spawn(move || {
+                while let Ok(Some((len, id))) = read_header(&mut reader) {
+                    let reply = if len > max_size {
+                        tracing::warn!(id, len, max = max_size, "reply is too large");
+
+                        match skip_payload(&mut reader, len) {
+                            Ok(()) => Err(Error::MessageTooLarge),
+                            Err(_) => break,
+                        }
+                    } else {
+                        match read_payload(&mut reader, len) {
+                            Ok(buf) => Ok(buf),
+                            Err(_) => break,
+                        }
+                    };
+
+                    p.route(id, reply);
+                }
+
+                p.close();
+                reader.shutdown(Shutdown::Both).ok();
+            })?;
+
+        Ok(Connection {
+            encoding,
+            writer: Mutex::new(stream),
+            pending,
+            last_used: Mutex::new(Instant::now()),
+        })
+    }
+
+    /// Send the request and wait for the reply until the deadline.
+    pub(crate) fn call(&self, bin: &[u8], deadline: Option<Instant>) -> Result<Vec<u8>, Error> {
+        *self.last_used.lock().unwrap() = Instant::now();
+
+        let (tx, rx) = mpsc::channel();
+        let id = self.pending.insert(tx)?;
+
+        let res = write_frame(&mut *self.writer.lock().unwrap(), id, bin);
+        if let Err(e) = res {
+            self.close();
+            return Err(Error::from(e));
+        }
+
+        let deadline = match deadline {
+            Some(deadline) => deadline,
+            None => return rx.recv().map_err(|_| Error::from(closed_error()))?,
+        };
+
+        match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(reply) => reply,
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                // A reply coming later is dropped by the reading thread.
+                self.pending.remove(id);
+
+                Err(Error::from(deadline_error()))
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(Error::from(closed_error())),
+        }
+    }
+
+    /// Return the encoding negotiated with the server.
+    pub(crate) fn encoding(&self) -> &str {
+        &self.encoding
+    }
+
+    /// Return the number of requests waiting for a reply.
+    pub(crate) fn in_flight(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Return the instant of the last request sent.
+    pub(crate) fn last_used(&self) -> Instant {
+        *self.last_used.lock().unwrap()
+    }
+
+    /// Return true when the connection can't be used anymore.
+    pub(crate) fn is_closed(&self) -> bool {
+        self.pending.is_closed()
+    }
+
+    /// Close the connection which will also stop the reading thread.
+    fn close(&self) {
+        self.writer.lock().unwrap().shutdown(Shutdown::Both).ok();
+    }
+}
+
+impl<S: Stream> Drop for Connection<S> {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
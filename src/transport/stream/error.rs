@@ -0,0 +1,163 @@
+use super::super::codec::CodecError;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum Error {
+    /// The connection failed, with the kind telling whether it timed out,
+    /// was refused or was reset among others.
+    IoError(IoError),
+    /// The payload can't be encoded or decoded.
+    SerdeError(CodecError),
+    NoSocketAddress,
+    /// The address is not the path of a Unix domain socket.
+    NoUnixPath,
+    NotRunning,
+    /// The certificates or the keys of the TLS configuration can't be used.
+    InvalidTlsConfig(String),
+    /// The handshake sent by the peer can't be understood.
+    InvalidHandshake,
+    /// The version of the protocol is not supported by the server.
+    UnsupportedVersion(u16),
+    /// The request sent by the client can't be understood.
+    InvalidRequest,
+    /// The reply sent by the server can't be understood.
+    InvalidReply,
+    /// The server rejected the request because its queue is full.
+    ServerBusy,
+    /// The server failed to process the request because the implementation
+    /// panicked with the given message.
+    Internal(String),
+    /// The message is larger than the maximum size accepted by the peer
+    /// reading it.
+    MessageTooLarge,
+    /// The server can't understand the request for the given reason, e.g.
+    /// its message doesn't match the requests of the service.
+    BadRequest(String),
+}
+
+impl Error {
+    /// Get the kind of the IO error, or None when the error is not one.
+    pub fn io_kind(&self) -> Option<io::ErrorKind> {
+        match self {
+            Error::IoError(err) => Some(err.kind()),
+            _ => None,
+        }
+    }
+
+    /// Return true when the peer didn't answer in time.
+    pub fn is_timeout(&self) -> bool {
+        self.io_kind() == Some(io::ErrorKind::TimedOut)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        // Codec errors are wrapped when they happen while reading or writing
+        // a stream.
+        if let Some(err) = err.get_ref().and_then(|e| e.downcast_ref::<CodecError>()) {
+            return Error::SerdeError(err.clone());
+        }
+
+        Error::IoError(IoError::from(err))
+    }
+}
+
+impl From<CodecError> for Error {
+    fn from(err: CodecError) -> Self {
+        Error::SerdeError(err)
+    }
+}
+
+/// IO error that keeps the kind of the original error so that it can be
+/// matched on after being sent to a peer.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IoError {
+    #[serde(with = "kind")]
+    kind: io::ErrorKind,
+    message: String,
+}
+
+impl IoError {
+    pub fn new(kind: io::ErrorKind, message: String) -> IoError {
+        IoError { kind, message }
+    }
+
+    pub fn kind(&self) -> io::ErrorKind {
+        self.kind
+    }
+
+    pub fn get_message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl From<io::Error> for IoError {
+    fn from(err: io::Error) -> Self {
+        let kind = match err.kind() {
+            // Blocking sockets only report that they would block when the
+            // read or write timeout elapses.
+            io::ErrorKind::WouldBlock => io::ErrorKind::TimedOut,
+            kind => kind,
+        };
+
+        IoError::new(kind, err.to_string())
+    }
+}
+
+/// Serialization of the kind of an IO error by its name. Kinds unknown to
+/// the peer are read as Other.
+mod kind {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::io::ErrorKind;
+
+    const KINDS: &[(ErrorKind, &str)] = &[
+        (ErrorKind::NotFound, "NotFound"),
+        (ErrorKind::PermissionDenied, "PermissionDenied"),
+        (ErrorKind::ConnectionRefused, "ConnectionRefused"),
+        (ErrorKind::ConnectionReset, "ConnectionReset"),
+        (ErrorKind::ConnectionAborted, "ConnectionAborted"),
+        (ErrorKind::NotConnected, "NotConnected"),
+        (ErrorKind::AddrInUse, "AddrInUse"),
+        (ErrorKind::AddrNotAvailable, "AddrNotAvailable"),
+        (ErrorKind::BrokenPipe, "BrokenPipe"),
+        (ErrorKind::AlreadyExists, "AlreadyExists"),
+        (ErrorKind::WouldBlock, "WouldBlock"),
+        (ErrorKind::InvalidInput, "InvalidInput"),
+        (ErrorKind::InvalidData, "InvalidData"),
+        (ErrorKind::TimedOut, "TimedOut"),
+        (ErrorKind::WriteZero, "WriteZero"),
+        (ErrorKind::Interrupted, "Interrupted"),
+        (ErrorKind::Unsupported, "Unsupported"),
+        (ErrorKind::UnexpectedEof, "UnexpectedEof"),
+        (ErrorKind::OutOfMemory, "OutOfMemory"),
+        (ErrorKind::Other, "Other"),
+    ];
+
+    pub fn serialize<S: Serializer>(kind: &ErrorKind, s: S) -> Result<S::Ok, S::Error> {
+        let name = KINDS
+            .iter()
+            .find(|(k, _)| k == kind)
+            .map_or("Other", |(_, name)| name);
+
+        s.serialize_str(name)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<ErrorKind, D::Error> {
+        let name = String::deserialize(d)?;
+
+        Ok(KINDS
+            .iter()
+            .find(|(_, n)| *n == name)
+            .map_or(ErrorKind::Other, |(kind, _)| *kind))
+    }
+}
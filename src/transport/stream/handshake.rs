@@ -0,0 +1,222 @@
+use super::super::codec::{Codec, JsonCodec};
+use super::{closed_error, Error};
+use std::convert::TryFrom;
+
+/// Version of the protocol spoken by this implementation.
+pub(crate) const PROTOCOL_VERSION: u16 = 1;
+/// Oldest version of the protocol that is still accepted. It is the only
+/// one as long as no other version has been released.
+pub(crate) const MIN_PROTOCOL_VERSION: u16 = PROTOCOL_VERSION;
+
+/// Bytes starting every handshake so that a peer speaking something else
+/// is detected early.
+const MAGIC: &[u8; 4] = b"RPC\0";
+
+const STATUS_ACCEPTED: u8 = 0;
+const STATUS_UNSUPPORTED_VERSION: u8 = 1;
+
+/// Return the encodings known by a transport using the codec. JSON is
+/// always part of them as it is the fallback between peers that don't
+/// share the same preferred codec, so that the peers of the crate always
+/// agree on an encoding.
+pub(crate) fn encodings<C: Codec>() -> Vec<String> {
+    let mut encodings = vec![C::NAME.to_string()];
+
+    if C::NAME != JsonCodec::NAME {
+        encodings.push(JsonCodec::NAME.to_string());
+    }
+
+    encodings
+}
+
+/// Answer the handshake of a client read from the first frame of the
+/// connection, with the encodings known by a transport using the codec.
+pub(crate) fn accept<C: Codec>(frame: Option<(u64, Vec<u8>)>) -> Result<Welcome, Error> {
+    match frame {
+        Some((_, buf)) => Hello::from_bytes(&buf[..])?.answer(&encodings::<C>()),
+        None => Err(Error::InvalidHandshake),
+    }
+}
+
+/// Return the encoding negotiated by the handshake from the answer of the
+/// server read from the first frame of the connection.
+pub(crate) fn negotiated(frame: Option<(u64, Vec<u8>)>, hello: &Hello) -> Result<String, Error> {
+    match frame {
+        Some((_, buf)) => Welcome::from_bytes(&buf[..], hello)?.into_encoding(),
+        None => Err(Error::from(closed_error())),
+    }
+}
+
+/// Message sent by the client when the connection is opened.
+pub(crate) struct Hello {
+    pub(crate) version: u16,
+    /// Encodings supported by the client by order of preference.
+    pub(crate) encodings: Vec<String>,
+}
+
+impl Hello {
+    pub(crate) fn new(encodings: Vec<String>) -> Hello {
+        Hello {
+            version: PROTOCOL_VERSION,
+            encodings,
+        }
+    }
+
+    /// Encode the handshake. The number of encodings and the length of
+    /// their names are sent on a byte, so a longer list or name is refused
+    /// rather than truncated.
+    pub(crate) fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(MAGIC);
+        buf.extend_from_slice(&self.version.to_be_bytes());
+        buf.push(u8::try_from(self.encodings.len()).map_err(|_| Error::InvalidHandshake)?);
+
+        for encoding in &self.encodings {
+            write_str(&mut buf, encoding)?;
+        }
+
+        Ok(buf)
+    }
+
+    pub(crate) fn from_bytes(buf: &[u8]) -> Result<Hello, Error> {
+        if buf.len() < MAGIC.len() || &buf[..MAGIC.len()] != MAGIC {
+            return Err(Error::InvalidHandshake);
+        }
+
+        let mut r = &buf[MAGIC.len()..];
+        let version = read_u16(&mut r)?;
+        let n = read_u8(&mut r)?;
+
+        let mut encodings = Vec::with_capacity(n as usize);
+        for _ in 0..n {
+            encodings.push(read_str(&mut r)?);
+        }
+
+        Ok(Hello { version, encodings })
+    }
+
+    /// Produce the answer of a server supporting the given encodings. The
+    /// first encoding of the client known by the server is chosen. A client
+    /// without any of them, not even JSON, doesn't speak the protocol.
+    pub(crate) fn answer(&self, supported: &[String]) -> Result<Welcome, Error> {
+        if self.version < MIN_PROTOCOL_VERSION {
+            return Ok(Welcome::Rejected(Error::UnsupportedVersion(self.version)));
+        }
+
+        match self.encodings.iter().find(|e| supported.contains(e)) {
+            Some(encoding) => Ok(Welcome::Accepted {
+                version: self.version.min(PROTOCOL_VERSION),
+                encoding: encoding.clone(),
+            }),
+            None => Err(Error::InvalidHandshake),
+        }
+    }
+}
+
+/// Answer of the server to the handshake of a client.
+pub(crate) enum Welcome {
+    /// The connection can be used with the given version of the protocol
+    /// and the messages encoded with the given codec.
+    Accepted { version: u16, encoding: String },
+    /// The server refuses the connection because of the version of the
+    /// client and will close it.
+    Rejected(Error),
+}
+
+impl Welcome {
+    pub(crate) fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        let mut buf = Vec::new();
+
+        match self {
+            Welcome::Accepted { version, encoding } => {
+                buf.push(STATUS_ACCEPTED);
+                buf.extend_from_slice(&version.to_be_bytes());
+                write_str(&mut buf, encoding)?;
+            }
+            Welcome::Rejected(_) => {
+                buf.push(STATUS_UNSUPPORTED_VERSION);
+                buf.extend_from_slice(&MIN_PROTOCOL_VERSION.to_be_bytes());
+            }
+        }
+
+        Ok(buf)
+    }
+
+    pub(crate) fn from_bytes(buf: &[u8], hello: &Hello) -> Result<Welcome, Error> {
+        let mut r = buf;
+
+        match read_u8(&mut r)? {
+            STATUS_ACCEPTED => {
+                let version = read_u16(&mut r)?;
+                if !(MIN_PROTOCOL_VERSION..=hello.version).contains(&version) {
+                    return Err(Error::UnsupportedVersion(version));
+                }
+
+                Ok(Welcome::Accepted {
+                    version,
+                    encoding: read_str(&mut r)?,
+                })
+            }
+            STATUS_UNSUPPORTED_VERSION => Ok(Welcome::Rejected(Error::UnsupportedVersion(hello.version))),
+            _ => Err(Error::InvalidHandshake),
+        }
+    }
+
+    /// Return the negotiated encoding or the reason of the rejection.
+    pub(crate) fn into_encoding(self) -> Result<String, Error> {
+        match self {
+            Welcome::Accepted { encoding, .. } => Ok(encoding),
+            Welcome::Rejected(e) => Err(e),
+        }
+    }
+}
+
+fn write_str(buf: &mut Vec<u8>, value: &str) -> Result<(), Error> {
+    buf.push(u8::try_from(value.len()).map_err(|_| Error::InvalidHandshake)?);
+    buf.extend_from_slice(value.as_bytes());
+    Ok(())
+}
+
+fn read_u8(r: &mut &[u8]) -> Result<u8, Error> {
+    match r.split_first() {
+        Some((v, rest)) => {
+            *r = rest;
+            Ok(*v)
+        }
+        None => Err(Error::InvalidHandshake),
+    }
+}
+
+fn read_u16(r: &mut &[u8]) -> Result<u16, Error> {
+    Ok(u16::from_be_bytes([read_u8(r)?, read_u8(r)?]))
+}
+
+fn read_str(r: &mut &[u8]) -> Result<String, Error> {
+    let len = read_u8(r)? as usize;
+    if r.len() < len {
+        return Err(Error::InvalidHandshake);
+    }
+
+    let (value, rest) = r.split_at(len);
+    *r = rest;
+
+    String::from_utf8(value.to_vec()).map_err(|_| Error::InvalidHandshake)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn too_long() {
+        let hello = Hello::new(vec!["x".repeat(256)]);
+        assert_eq!(hello.to_bytes().err(), Some(Error::InvalidHandshake));
+
+        let hello = Hello::new(vec![String::from("json"); 256]);
+        assert_eq!(hello.to_bytes().err(), Some(Error::InvalidHandshake));
+
+        let hello = Hello::new(vec!["x".repeat(255); 255]);
+        let decoded = Hello::from_bytes(&hello.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.encodings, hello.encodings);
+    }
+}
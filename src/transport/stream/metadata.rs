@@ -0,0 +1,56 @@
+use super::super::super::Metadata;
+
+/// Write the metadata as the number of entries followed by the length
+/// prefixed keys and values.
+pub(crate) fn write_metadata(buf: &mut Vec<u8>, metadata: &Metadata) {
+    buf.extend_from_slice(&(metadata.len() as u32).to_be_bytes());
+
+    for (key, value) in metadata {
+        write_string(buf, key);
+        write_string(buf, value);
+    }
+}
+
+/// Read the metadata at the beginning of the buffer which is advanced
+/// past it. Nothing is returned when the metadata is malformed.
+pub(crate) fn read_metadata(buf: &mut &[u8]) -> Option<Metadata> {
+    let count = read_u32(buf)?;
+
+    let mut metadata = Metadata::new();
+    for _ in 0..count {
+        let key = read_string(buf)?;
+        let value = read_string(buf)?;
+
+        metadata.insert(key, value);
+    }
+
+    Some(metadata)
+}
+
+fn write_string(buf: &mut Vec<u8>, value: &str) {
+    buf.extend_from_slice(&(value.len() as u32).to_be_bytes());
+    buf.extend_from_slice(value.as_bytes());
+}
+
+fn read_u32(buf: &mut &[u8]) -> Option<u32> {
+    if buf.len() < 4 {
+        return None;
+    }
+
+    let (head, tail) = buf.split_at(4);
+    *buf = tail;
+
+    Some(u32::from_be_bytes([head[0], head[1], head[2], head[3]]))
+}
+
+fn read_string(buf: &mut &[u8]) -> Option<String> {
+    let len = read_u32(buf)? as usize;
+    if buf.len() < len {
+        return None;
+    }
+
+    let (head, tail) = buf.split_at(len);
+    *buf = tail;
+
+    String::from_utf8(head.to_vec()).ok()
+}
@@ -0,0 +1,829 @@
+mod connection;
+mod error;
+pub(crate) mod handshake;
+mod metadata;
+pub(crate) mod pending;
+mod pool;
+pub(crate) mod reply;
+pub(crate) mod request;
+#[cfg(feature = "tls")]
+pub(crate) mod tls;
+mod transport;
+mod worker;
+
+pub use self::error::{Error, IoError};
+pub use self::pool::PoolConfig;
+pub use self::transport::{Accept, StreamClientTransport, StreamServerTransport};
+pub use self::worker::{Overflow, WorkerConfig};
+pub(crate) use self::pool::Pool;
+
+use super::super::{
+    executor::Executor,
+    group::Address,
+    metrics::Recorder,
+    CallOptions,
+    CancellationToken,
+    Context,
+    Metadata,
+    PeerIdentity,
+    RequestProcessor,
+};
+use super::codec::{decode_with, encode_with, Codec};
+use super::panic_message;
+use self::reply::Reply;
+use self::request::Request;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::HashMap;
+use std::{io, io::{Read, Write}};
+use std::net::{Shutdown, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::{Duration, Instant};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+pub(crate) const WAIT_TIMEOUT: Option<Duration> = Some(Duration::from_millis(100));
+pub(crate) const READ_TIMEOUT: Option<Duration> = Some(Duration::from_millis(5000));
+pub(crate) const WRITE_TIMEOUT: Option<Duration> = Some(Duration::from_millis(5000));
+
+/// Size in bytes of the largest payload of a message read by default.
+pub(crate) const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+/// Number of connections a server serves at the same time by default.
+pub(crate) const MAX_CONNECTIONS: usize = 1024;
+
+/// Settings of the connections accepted by a server.
+#[derive(Clone, Debug)]
+pub struct ServeOptions {
+    /// Behavior when a request comes while the queue of the executor is
+    /// full.
+    pub(crate) overflow: Overflow,
+    /// Size in bytes of the largest request accepted.
+    pub(crate) max_message_size: usize,
+    /// Number of connections served at the same time, each by a thread of
+    /// its own. Connections above are closed as soon as accepted.
+    pub(crate) max_connections: usize,
+    /// Duration after which a connection without requests is closed.
+    pub(crate) read_timeout: Option<Duration>,
+    /// Duration after which writing a reply fails.
+    pub(crate) write_timeout: Option<Duration>,
+    /// Sink of the measurements of the server.
+    pub(crate) metrics: Recorder,
+}
+
+impl ServeOptions {
+    pub(crate) fn new(overflow: Overflow) -> ServeOptions {
+        ServeOptions {
+            overflow,
+            max_message_size: MAX_MESSAGE_SIZE,
+            max_connections: MAX_CONNECTIONS,
+            read_timeout: READ_TIMEOUT,
+            write_timeout: WRITE_TIMEOUT,
+            metrics: Recorder::default(),
+        }
+    }
+}
+
+/// Number of bytes of the header that prefixes every frame with the
+/// length of its payload and the identifier of the request.
+pub(crate) const FRAME_HEADER_SIZE: usize = 12;
+
+/// Produce the header of a frame with the length of the payload and the
+/// request identifier.
+pub(crate) fn encode_header(len: usize, id: u64) -> io::Result<[u8; FRAME_HEADER_SIZE]> {
+    if len > u32::MAX as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "frame payload is too large",
+        ));
+    }
+
+    let mut header = [0u8; FRAME_HEADER_SIZE];
+    header[..4].copy_from_slice(&(len as u32).to_be_bytes());
+    header[4..].copy_from_slice(&id.to_be_bytes());
+
+    Ok(header)
+}
+
+/// Extract the length of the payload and the request identifier from the
+/// header of a frame.
+pub(crate) fn decode_header(header: &[u8; FRAME_HEADER_SIZE]) -> (usize, u64) {
+    let mut len = [0u8; 4];
+    len.copy_from_slice(&header[..4]);
+    let mut id = [0u8; 8];
+    id.copy_from_slice(&header[4..]);
+
+    (u32::from_be_bytes(len) as usize, u64::from_be_bytes(id))
+}
+
+/// Write the payload to the stream prefixed by its length and the request
+/// identifier. The length lets the peer know where the message ends without
+/// closing the connection and the identifier is used to match a reply with
+/// its request when several of them are in flight.
+fn write_frame<W: Write>(w: &mut W, id: u64, buf: &[u8]) -> io::Result<()> {
+    w.write_all(&encode_frame(id, buf)?[..])?;
+    w.flush()
+}
+
+/// Produce the frame carrying the payload, written at once so that the
+/// frames of concurrent requests are never interleaved.
+pub(crate) fn encode_frame(id: u64, buf: &[u8]) -> io::Result<Vec<u8>> {
+    let header = encode_header(buf.len(), id)?;
+
+    let mut frame = Vec::with_capacity(FRAME_HEADER_SIZE + buf.len());
+    frame.extend_from_slice(&header);
+    frame.extend_from_slice(buf);
+
+    Ok(frame)
+}
+
+/// Read the header of the next frame from the stream and return the length
+/// of the payload with the request identifier. None is returned when the
+/// peer closed the connection in between two frames.
+fn read_header<R: Read>(r: &mut R) -> io::Result<Option<(usize, u64)>> {
+    let mut header = [0u8; FRAME_HEADER_SIZE];
+    let mut n = 0;
+
+    while n < FRAME_HEADER_SIZE {
+        match r.read(&mut header[n..]) {
+            Ok(0) if n == 0 => return Ok(None),
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(k) => n += k,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+            // A timeout only means that the connection is idle in between
+            // two frames.
+            Err(ref e) if n > 0 && is_timeout(e) => {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "frame header is incomplete"));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(Some(decode_header(&header)))
+}
+
+/// Read the payload of the frame whose header has just been read.
+fn read_payload<R: Read>(r: &mut R, len: usize) -> io::Result<Vec<u8>> {
+    let mut buf = vec![0u8; len];
+    r.read_exact(&mut buf[..])?;
+
+    Ok(buf)
+}
+
+/// Discard the payload of the frame whose header has just been read so
+/// that the next frame can be read, without keeping it in memory.
+fn skip_payload<R: Read>(r: &mut R, len: usize) -> io::Result<()> {
+    let n = io::copy(&mut r.take(len as u64), &mut io::sink())?;
+    if n < len as u64 {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    Ok(())
+}
+
+/// Read the next frame from the stream and return the request identifier
+/// with the payload. None is returned when the peer closed the connection
+/// in between two frames. The payload is not read when it is larger than
+/// the maximum size.
+fn read_frame<R: Read>(r: &mut R, max_size: usize) -> Result<Option<(u64, Vec<u8>)>, Error> {
+    let (len, id) = match read_header(r)? {
+        Some(header) => header,
+        None => return Ok(None),
+    };
+
+    check_frame_size(len, max_size)?;
+
+    Ok(Some((id, read_payload(r, len)?)))
+}
+
+/// Fail when the payload announced by the header of a frame is larger than
+/// the maximum size.
+pub(crate) fn check_frame_size(len: usize, max_size: usize) -> Result<(), Error> {
+    if len > max_size {
+        return Err(Error::MessageTooLarge);
+    }
+
+    Ok(())
+}
+
+/// Error of the requests sent on a connection that is closed.
+pub(crate) fn closed_error() -> io::Error {
+    io::Error::new(io::ErrorKind::ConnectionAborted, "connection closed")
+}
+
+/// Error of the requests whose reply didn't come before the deadline.
+pub(crate) fn deadline_error() -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, "deadline has elapsed")
+}
+
+/// Return true when the error means that nothing has been received
+/// before the read timeout elapsed.
+fn is_timeout(err: &io::Error) -> bool {
+    err.kind() == io::ErrorKind::WouldBlock || err.kind() == io::ErrorKind::TimedOut
+}
+
+/// A byte stream connecting a client and a server which can be cloned to
+/// read and write from different threads.
+pub trait Stream: Read + Write + Send + Sized + 'static {
+    /// Settings required to open a stream on top of the address.
+    type Connector: Send + Sync;
+
+    /// Open a stream to the server at the given address, failing if it takes
+    /// longer than the timeout.
+    fn connect(addr: &Address, connector: &Self::Connector, timeout: Option<Duration>) -> Result<Self, Error>;
+
+    fn try_clone(&self) -> io::Result<Self>;
+
+    fn shutdown(&self, how: Shutdown) -> io::Result<()>;
+
+    fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()>;
+
+    fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()>;
+
+    /// Return the identity of the peer when the stream is authenticated.
+    fn peer_identity(&self) -> Option<Arc<PeerIdentity>> {
+        None
+    }
+}
+
+impl Stream for TcpStream {
+    type Connector = ();
+
+    fn connect(addr: &Address, _: &(), timeout: Option<Duration>) -> Result<Self, Error> {
+        match (addr.get_socket_addr(), timeout) {
+            (Some(addr), Some(timeout)) => Ok(TcpStream::connect_timeout(&addr, timeout)?),
+            (Some(addr), None) => Ok(TcpStream::connect(addr)?),
+            (None, _) => Err(Error::NoSocketAddress),
+        }
+    }
+
+    fn try_clone(&self) -> io::Result<Self> {
+        TcpStream::try_clone(self)
+    }
+
+    fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        TcpStream::shutdown(self, how)
+    }
+
+    fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, dur)
+    }
+
+    fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_write_timeout(self, dur)
+    }
+}
+
+#[cfg(unix)]
+impl Stream for UnixStream {
+    type Connector = ();
+
+    /// Connecting to a local socket doesn't wait for the server so there is
+    /// no need for a timeout.
+    fn connect(addr: &Address, _: &(), _: Option<Duration>) -> Result<Self, Error> {
+        match addr {
+            Address::Unix(path) => Ok(UnixStream::connect(path)?),
+            _ => Err(Error::NoUnixPath),
+        }
+    }
+
+    fn try_clone(&self) -> io::Result<Self> {
+        UnixStream::try_clone(self)
+    }
+
+    fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        UnixStream::shutdown(self, how)
+    }
+
+    fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        UnixStream::set_read_timeout(self, dur)
+    }
+
+    fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        UnixStream::set_write_timeout(self, dur)
+    }
+}
+
+/// Connections opened to a server. A clone of each stream is kept so that
+/// the server can stop reading new requests when it shuts down.
+pub(crate) struct Connections<S: Stream> {
+    inner: Mutex<ConnectionsInner<S>>,
+    /// Number of requests of the server queued or running in the executor.
+    jobs: AtomicUsize,
+}
+
+struct ConnectionsInner<S> {
+    closing: bool,
+    next_id: u64,
+    streams: HashMap<u64, (S, CancellationToken)>,
+    threads: usize,
+}
+
+/// Thread serving a connection, counted by the server until it is dropped.
+pub(crate) struct Slot<S: Stream> {
+    conns: Arc<Connections<S>>,
+}
+
+impl<S: Stream> Drop for Slot<S> {
+    fn drop(&mut self) {
+        self.conns.inner.lock().unwrap().threads -= 1;
+    }
+}
+
+/// Registration of a connection which is removed when dropped.
+struct Registration<S: Stream> {
+    conns: Arc<Connections<S>>,
+    id: u64,
+}
+
+/// Writing side of a connection shared by the requests. The connection
+/// stays registered until every reply has been written.
+struct Writer<S: Stream> {
+    stream: Mutex<S>,
+    in_flight: AtomicUsize,
+    metrics: Recorder,
+    /// Address of the server, which labels its measurements.
+    server: String,
+    registration: Registration<S>,
+}
+
+impl<S: Stream> Writer<S> {
+    /// Return the number of requests whose reply has not been written yet.
+    fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Write the reply of the request with the given identifier.
+    fn write(&self, id: u64, buf: &[u8]) -> io::Result<()> {
+        write_frame(&mut *self.stream.lock().unwrap(), id, buf)?;
+        self.metrics.record(|m| m.bytes_sent(&self.server, FRAME_HEADER_SIZE + buf.len()));
+
+        Ok(())
+    }
+
+    /// Write the reply of the request with the given identifier.
+    fn reply(&self, id: u64, reply: &Reply) -> io::Result<()> {
+        self.write(id, &reply.to_bytes()[..])
+    }
+}
+
+/// Request of a connection counted as in flight until it is dropped,
+/// whether its reply has been written or not. It is also counted in the
+/// depth of the queue of the server.
+struct InFlight<S: Stream>(Arc<Writer<S>>);
+
+impl<S: Stream> InFlight<S> {
+    fn new(writer: &Arc<Writer<S>>) -> InFlight<S> {
+        writer.in_flight.fetch_add(1, Ordering::SeqCst);
+        let jobs = writer.registration.conns.jobs.fetch_add(1, Ordering::SeqCst) + 1;
+        writer.metrics.record(|m| m.queue_depth(&writer.server, jobs));
+
+        InFlight(Arc::clone(writer))
+    }
+}
+
+impl<S: Stream> Drop for InFlight<S> {
+    fn drop(&mut self) {
+        let writer = &self.0;
+
+        writer.in_flight.fetch_sub(1, Ordering::SeqCst);
+        let jobs = writer.registration.conns.jobs.fetch_sub(1, Ordering::SeqCst) - 1;
+        writer.metrics.record(|m| m.queue_depth(&writer.server, jobs));
+    }
+}
+
+impl<S: Stream> Connections<S> {
+    pub(crate) fn new() -> Connections<S> {
+        Connections {
+            inner: Mutex::new(ConnectionsInner {
+                closing: false,
+                next_id: 0,
+                streams: HashMap::new(),
+                threads: 0,
+            }),
+            jobs: AtomicUsize::new(0),
+        }
+    }
+
+    /// Count a new thread serving a connection, unless the maximum number
+    /// of them is already reached.
+    pub(crate) fn acquire(conns: &Arc<Self>, max: usize) -> Option<Slot<S>> {
+        let mut inner = conns.inner.lock().unwrap();
+        if inner.threads >= max {
+            return None;
+        }
+
+        inner.threads += 1;
+
+        Some(Slot {
+            conns: Arc::clone(conns),
+        })
+    }
+
+    /// Keep a clone of the stream until the registration is dropped, with
+    /// the token cancelling its requests. It fails when the server is
+    /// shutting down.
+    fn register(conns: &Arc<Self>, stream: &S, token: &CancellationToken) -> Result<Registration<S>, Error> {
+        let mut inner = conns.inner.lock().unwrap();
+        if inner.closing {
+            return Err(Error::NotRunning);
+        }
+
+        let id = inner.next_id;
+        inner.next_id += 1;
+        inner.streams.insert(id, (stream.try_clone()?, token.clone()));
+
+        Ok(Registration {
+            conns: Arc::clone(conns),
+            id,
+        })
+    }
+
+    fn shutdown(&self, how: Shutdown) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.closing = true;
+
+        for (stream, token) in inner.streams.values() {
+            // The requests still running are aborted when the connection is
+            // closed for good.
+            if how == Shutdown::Both {
+                token.cancel();
+            }
+
+            // The connection might be closed already.
+            stream.shutdown(how).ok();
+        }
+    }
+
+    fn is_closing(&self) -> bool {
+        self.inner.lock().unwrap().closing
+    }
+
+    /// Stop reading the requests of the connections and wait for the ones
+    /// in progress to complete until the deadline, then close the
+    /// connections. It returns the number of requests aborted.
+    pub(crate) fn drain<E: Executor>(&self, pool: &E, deadline: Instant) -> usize {
+        // The replies can still be written.
+        self.shutdown(Shutdown::Read);
+
+        let aborted = pool.shutdown(deadline);
+
+        // Clients waiting for an aborted request are told by closing the
+        // connection.
+        self.shutdown(Shutdown::Both);
+
+        aborted
+    }
+}
+
+impl<S: Stream> Drop for Registration<S> {
+    fn drop(&mut self) {
+        self.conns.inner.lock().unwrap().streams.remove(&self.id);
+    }
+}
+
+/// Cancel the requests of a connection when the server stops reading it,
+/// unless it has been disarmed because the replies are still expected.
+pub(crate) struct CancelOnDrop(Option<CancellationToken>);
+
+impl CancelOnDrop {
+    pub(crate) fn new() -> CancelOnDrop {
+        CancelOnDrop(Some(CancellationToken::new()))
+    }
+
+    pub(crate) fn token(&self) -> &CancellationToken {
+        self.0.as_ref().expect("token is only taken when dropped")
+    }
+
+    pub(crate) fn disarm(&mut self) {
+        self.0.take();
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if let Some(token) = self.0.take() {
+            token.cancel();
+        }
+    }
+}
+
+/// Read the handshake of the client and answer with the encoding that will
+/// be used by the connection, or with the reason of the rejection.
+fn accept_handshake<S: Stream, C: Codec>(stream: &mut S, max_size: usize) -> Result<String, Error> {
+    let welcome = handshake::accept::<C>(read_frame(stream, max_size)?)?;
+
+    write_frame(stream, 0, &welcome.to_bytes()?)?;
+
+    welcome.into_encoding()
+}
+
+/// Read the request carried by a frame and decode its message with the
+/// encoding of the connection.
+pub(crate) fn decode_request<C, Req>(buf: Vec<u8>, encoding: &str) -> Result<(Request, Req), Error>
+where
+    C: Codec,
+    Req: DeserializeOwned,
+{
+    let request = Request::from_bytes(buf)?;
+    let req = decode_with::<C, _>(encoding, &request.body[..])?;
+
+    Ok((request, req))
+}
+
+/// Read the requests coming from a connection until it is closed. Each
+/// request is processed by the pool of workers independently and the
+/// replies are written back as soon as they are ready.
+pub(crate) fn serve_connection<S, E, Req, Rep, C>(
+    mut stream: S,
+    conns: Arc<Connections<S>>,
+    pool: Arc<E>,
+    opts: ServeOptions,
+    f: Arc<Box<RequestProcessor<Req, Rep>>>,
+    out_addr: Address,
+    in_addr: Address,
+) -> Result<(), Error>
+where
+    S: Stream,
+    E: Executor,
+    Req: DeserializeOwned + Send + 'static,
+    Rep: Serialize + 'static,
+    C: Codec,
+{
+    stream.set_read_timeout(opts.read_timeout)?;
+    stream.set_write_timeout(opts.write_timeout)?;
+
+    let encoding = accept_handshake::<S, C>(&mut stream, opts.max_message_size)?;
+    let encoding = Arc::new(encoding);
+    let mut cancel = CancelOnDrop::new();
+    let server = out_addr.to_string();
+    let ctx = Context::new(in_addr, out_addr)
+        .with_peer(stream.peer_identity())
+        .with_cancellation(cancel.token())
+        .with_metrics(opts.metrics.clone());
+    let writer = Arc::new(Writer {
+        stream: Mutex::new(stream.try_clone()?),
+        in_flight: AtomicUsize::new(0),
+        metrics: opts.metrics.clone(),
+        server,
+        registration: Connections::register(&conns, &stream, cancel.token())?,
+    });
+    writer.metrics.record(|m| m.connection_accepted(&writer.server));
+
+    // The connection stays open so that the client can send several
+    // requests until it decides to close it. The requests in progress are
+    // cancelled when the client is gone.
+    loop {
+        let (len, id) = match read_header(&mut stream) {
+            Ok(Some(header)) => header,
+            // The client closed the connection, unless the server stopped
+            // reading because it is shutting down.
+            Ok(None) => {
+                if conns.is_closing() {
+                    cancel.disarm();
+                }
+                return Ok(());
+            }
+            // The client is still waiting for replies so it might send
+            // more requests.
+            Err(ref e) if is_timeout(e) && writer.in_flight() > 0 => continue,
+            // The connection has been idle for too long but the client
+            // might still wait for replies.
+            Err(ref e) if is_timeout(e) => {
+                cancel.disarm();
+                return Ok(());
+            }
+            // TLS reports the read side shut down by the server as an
+            // unexpected end of the stream, which must not cancel the
+            // requests being drained either.
+            Err(_) if conns.is_closing() => {
+                cancel.disarm();
+                return Ok(());
+            }
+            Err(e) => return Err(Error::from(e)),
+        };
+
+        if len > opts.max_message_size {
+            tracing::warn!(id, peer = %ctx.get_in_addr(), len, max = opts.max_message_size, "request is too large");
+
+            // The connection can still be used for the next requests.
+            skip_payload(&mut stream, len)?;
+            writer.reply(id, &Reply::TooLarge)?;
+            continue;
+        }
+
+        let buf = read_payload(&mut stream, len)?;
+        writer.metrics.record(|m| m.bytes_received(&writer.server, FRAME_HEADER_SIZE + buf.len()));
+
+        // A request that can't be understood only fails itself.
+        let (request, req): (_, Req) = match decode_request::<C, _>(buf, &encoding) {
+            Ok(v) => v,
+            Err(e) => {
+                tracing::warn!(id, peer = %ctx.get_in_addr(), error = %e, "invalid request");
+
+                writer.reply(id, &Reply::BadRequest(e.to_string()))?;
+                continue;
+            }
+        };
+
+        let job = {
+            let f = Arc::clone(&f);
+            let in_flight = InFlight::new(&writer);
+            let encoding = Arc::clone(&encoding);
+            let ctx = ctx
+                .clone()
+                .with_deadline(request.deadline(Instant::now()))
+                .with_metadata(request.metadata);
+            // The request is only moved into the processor.
+            let req = AssertUnwindSafe(req);
+
+            Box::new(move || -> io::Result<()> {
+                // The client stopped waiting while the request was queued.
+                if ctx.is_cancelled() {
+                    tracing::debug!(id, peer = %ctx.get_in_addr(), "request cancelled before being processed");
+                    return Ok(());
+                }
+
+                // The handler attaches the metadata of the reply to its own
+                // clone of the context.
+                let reply_ctx = ctx.clone();
+
+                let out = match panic::catch_unwind(move || f(req.0, ctx)) {
+                    Ok(reply) => {
+                        let body = encode_with::<C, _>(&encoding, &reply)?;
+                        Reply::Ok(reply_ctx.take_reply_metadata(), body)
+                    }
+                    Err(e) => {
+                        let msg = panic_message(&*e);
+                        tracing::error!(id, panic = %msg, "request panicked");
+
+                        Reply::Internal(msg)
+                    }
+                };
+
+                in_flight.0.reply(id, &out)
+            })
+        };
+
+        let overflow = opts.overflow;
+        let res = match overflow {
+            Overflow::Block => pool.execute(job),
+            _ => pool.try_execute(job),
+        };
+
+        match res {
+            Ok(()) => (),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                tracing::warn!(id, peer = %ctx.get_in_addr(), ?overflow, "server is busy");
+
+                // The other requests of the connection are still served.
+                if overflow == Overflow::Reject {
+                    writer.reply(id, &Reply::Busy)?;
+                }
+            }
+            Err(e) => return Err(Error::from(e)),
+        }
+    }
+}
+
+/// Send the message over a connection of the pool and wait for the reply.
+/// Other requests can use the same connection meanwhile.
+/// The caller gives up when the deadline of the options, or the call
+/// timeout of the pool when there is none, elapses.
+pub(crate) fn send<S, C, Req, Rep>(pool: &Pool<S>, msg: Req, opts: &CallOptions) -> Result<(Rep, Metadata), Error>
+where
+    S: Stream,
+    C: Codec,
+    for<'de> Rep: Deserialize<'de>,
+    Req: Serialize,
+{
+    let deadline = pool.deadline(opts.deadline);
+    let conn = pool.checkout()?;
+
+    let bin = encode_with::<C, _>(conn.encoding(), &msg)?;
+    let req = Request::new(bin, deadline, opts.metadata.clone()).to_bytes();
+
+    let (metadata, buf) = Reply::from_bytes(conn.call(&req[..], deadline)?)?.into_body()?;
+
+    Ok((decode_with::<C, _>(conn.encoding(), &buf[..])?, metadata))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    /// Reader handing out the bytes in the given chunks, as a socket would
+    /// when a frame is split across several segments. An empty chunk stands
+    /// for the read timeout elapsing.
+    struct Chunks(VecDeque<Vec<u8>>);
+
+    impl Chunks {
+        fn new(chunks: &[&[u8]]) -> Chunks {
+            Chunks(chunks.iter().map(|chunk| chunk.to_vec()).collect())
+        }
+    }
+
+    impl Read for Chunks {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let mut chunk = match self.0.pop_front() {
+                Some(chunk) => chunk,
+                None => return Ok(0),
+            };
+
+            if chunk.is_empty() {
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+
+            let n = buf.len().min(chunk.len());
+            buf[..n].copy_from_slice(&chunk[..n]);
+            if n < chunk.len() {
+                self.0.push_front(chunk.split_off(n));
+            }
+
+            Ok(n)
+        }
+    }
+
+    fn frame(id: u64, payload: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        write_frame(&mut buf, id, payload).unwrap();
+
+        buf
+    }
+
+    #[test]
+    fn frames_on_one_stream() {
+        let mut buf = frame(1, b"first");
+        buf.extend(frame(2, b""));
+        buf.extend(frame(3, b"third"));
+        let mut r = &buf[..];
+
+        assert_eq!(read_frame(&mut r, 16).unwrap(), Some((1, b"first".to_vec())));
+        assert_eq!(read_frame(&mut r, 16).unwrap(), Some((2, Vec::new())));
+        assert_eq!(read_frame(&mut r, 16).unwrap(), Some((3, b"third".to_vec())));
+        assert_eq!(read_frame(&mut r, 16).unwrap(), None);
+    }
+
+    #[test]
+    fn frame_split_across_reads() {
+        let buf = frame(42, b"deadbeef");
+
+        // The header and the payload are both cut in the middle.
+        let mut r = Chunks::new(&[&buf[..3], &buf[3..10], &buf[10..14], &buf[14..]]);
+        assert_eq!(read_frame(&mut r, 16).unwrap(), Some((42, b"deadbeef".to_vec())));
+
+        // One byte at a time.
+        let bytes: Vec<&[u8]> = buf.chunks(1).collect();
+        let mut r = Chunks::new(&bytes);
+        assert_eq!(read_frame(&mut r, 16).unwrap(), Some((42, b"deadbeef".to_vec())));
+    }
+
+    #[test]
+    fn partial_frame() {
+        let buf = frame(42, b"deadbeef");
+
+        // The peer closes the connection in the middle of the header or of
+        // the payload.
+        let mut r = &buf[..5];
+        assert_eq!(read_header(&mut r).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+
+        let mut r = &buf[..FRAME_HEADER_SIZE + 3];
+        match read_frame(&mut r, 16) {
+            Err(Error::IoError(e)) => assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof),
+            res => panic!("unexpected result: {:?}", res),
+        }
+
+        // The read timeout elapses in the middle of the header, which can't
+        // be mistaken for an idle connection.
+        let mut r = Chunks::new(&[&buf[..5], &[]]);
+        assert_eq!(read_header(&mut r).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        // It only means that the connection is idle before the header.
+        let mut r = Chunks::new(&[&[]]);
+        assert_eq!(read_header(&mut r).unwrap_err().kind(), io::ErrorKind::WouldBlock);
+    }
+
+    #[test]
+    fn length_at_the_limit() {
+        let payload = vec![7u8; 16];
+
+        let buf = frame(1, &payload);
+        assert_eq!(read_frame(&mut &buf[..], 16).unwrap(), Some((1, payload.clone())));
+
+        let buf = frame(1, &payload[..15]);
+        assert_eq!(read_frame(&mut &buf[..], 15).unwrap(), Some((1, payload[..15].to_vec())));
+
+        let buf = frame(1, &payload);
+        match read_frame(&mut &buf[..], 15) {
+            Err(Error::MessageTooLarge) => (),
+            res => panic!("unexpected result: {:?}", res),
+        }
+
+        // The header can't announce more than the length holds.
+        assert!(encode_header(u32::MAX as usize, 1).is_ok());
+        assert_eq!(encode_header(u32::MAX as usize + 1, 1).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(decode_header(&encode_header(u32::MAX as usize, 9).unwrap()), (u32::MAX as usize, 9));
+    }
+}
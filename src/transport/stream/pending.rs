@@ -0,0 +1,134 @@
+use super::{closed_error, Error};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Mutex};
+
+/// Reply read for a request, or the reason why it can't be used.
+pub(crate) type ReplyResult = Result<Vec<u8>, Error>;
+
+/// Sending side of the channel a caller waits for its reply on.
+pub(crate) trait ReplySender {
+    fn send_reply(self, reply: ReplyResult);
+}
+
+impl ReplySender for mpsc::Sender<ReplyResult> {
+    fn send_reply(self, reply: ReplyResult) {
+        // The caller might have given up already.
+        self.send(reply).ok();
+    }
+}
+
+#[cfg(feature = "async")]
+impl ReplySender for tokio::sync::oneshot::Sender<ReplyResult> {
+    fn send_reply(self, reply: ReplyResult) {
+        self.send(reply).ok();
+    }
+}
+
+/// Requests of a connection waiting for a reply, shared by the callers and
+/// the reader of the replies of blocking and asynchronous clients alike.
+/// The map is set to None when the connection is closed so that no request
+/// can wait forever.
+pub(crate) struct Pending<T> {
+    senders: Mutex<Option<HashMap<u64, T>>>,
+    next_id: AtomicU64,
+}
+
+impl<T: ReplySender> Pending<T> {
+    pub(crate) fn new() -> Pending<T> {
+        Pending {
+            senders: Mutex::new(Some(HashMap::new())),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    /// Register a request that will be replied to on the sender and return
+    /// its identifier, or fail when the connection is closed.
+    pub(crate) fn insert(&self, tx: T) -> Result<u64, Error> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+
+        match self.senders.lock().unwrap().as_mut() {
+            Some(senders) => senders.insert(id, tx),
+            None => return Err(Error::from(closed_error())),
+        };
+
+        Ok(id)
+    }
+
+    /// Forget a request whose caller stopped waiting, so that a reply
+    /// coming later is dropped.
+    pub(crate) fn remove(&self, id: u64) {
+        if let Some(senders) = self.senders.lock().unwrap().as_mut() {
+            senders.remove(&id);
+        }
+    }
+
+    /// Send the reply read from the connection to the caller of the request.
+    pub(crate) fn route(&self, id: u64, reply: ReplyResult) {
+        let tx = match self.senders.lock().unwrap().as_mut() {
+            Some(senders) => senders.remove(&id),
+            None => None,
+        };
+
+        if let Some(tx) = tx {
+            tx.send_reply(reply);
+        }
+    }
+
+    /// Mark the connection as closed. Dropping the senders wakes up the
+    /// callers still waiting for a reply.
+    pub(crate) fn close(&self) {
+        self.senders.lock().unwrap().take();
+    }
+
+    /// Return the number of requests waiting for a reply.
+    pub(crate) fn len(&self) -> usize {
+        match self.senders.lock().unwrap().as_ref() {
+            Some(senders) => senders.len(),
+            None => 0,
+        }
+    }
+
+    /// Return true when the connection can't be used anymore.
+    pub(crate) fn is_closed(&self) -> bool {
+        self.senders.lock().unwrap().is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dispatch() {
+        let pending = Pending::new();
+
+        let (tx, first) = mpsc::channel();
+        let id = pending.insert(tx).unwrap();
+        let (tx, second) = mpsc::channel();
+        let other = pending.insert(tx).unwrap();
+        let (tx, third) = mpsc::channel();
+        let late = pending.insert(tx).unwrap();
+        assert_eq!(pending.len(), 3);
+
+        // The replies go to their caller whatever the order they come in.
+        pending.route(other, Ok(vec![2]));
+        pending.route(id, Ok(vec![1]));
+        assert_eq!(first.recv().unwrap(), Ok(vec![1]));
+        assert_eq!(second.recv().unwrap(), Ok(vec![2]));
+
+        // The reply of a request given up is dropped.
+        pending.remove(late);
+        pending.route(late, Ok(vec![3]));
+        assert!(third.recv().is_err());
+
+        let (tx, waiting) = mpsc::channel();
+        pending.insert(tx).unwrap();
+        pending.close();
+        assert!(pending.is_closed());
+        assert!(waiting.recv().is_err());
+
+        let (tx, _) = mpsc::channel();
+        assert!(pending.insert(tx).is_err());
+    }
+}
@@ -0,0 +1,148 @@
+use super::super::super::group::Address;
+use super::connection::Connection;
+use super::{Error, Stream, MAX_MESSAGE_SIZE};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Configuration of the pool of connections kept open by a client.
+#[derive(Clone, Debug)]
+pub struct PoolConfig {
+    /// Maximum number of connections kept open to the server.
+    pub max_idle: usize,
+    /// Duration after which an unused connection is closed. It should be
+    /// lower than the read timeout of the server so that the client never
+    /// picks a connection that the server is about to close.
+    pub idle_timeout: Duration,
+    /// Number of concurrent requests on a connection above which a new
+    /// connection is opened, as long as the pool is not full.
+    pub max_in_flight: usize,
+    /// Size in bytes of the largest reply accepted. A larger reply is
+    /// discarded and the request fails with MessageTooLarge.
+    pub max_message_size: usize,
+    /// Duration after which opening a connection and negotiating the
+    /// protocol with the server fails, or None to wait as long as needed.
+    pub connect_timeout: Option<Duration>,
+    /// Duration after which writing a request fails, or None to wait as
+    /// long as needed.
+    pub write_timeout: Option<Duration>,
+    /// Duration after which a request without a deadline of its own fails
+    /// with TimedOut, which is 30 seconds by default so that a hung server
+    /// can't block the caller forever, or None to wait for the reply as
+    /// long as needed.
+    pub call_timeout: Option<Duration>,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        PoolConfig {
+            max_idle: 4,
+            idle_timeout: Duration::from_millis(4000),
+            max_in_flight: 64,
+            max_message_size: MAX_MESSAGE_SIZE,
+            connect_timeout: Some(Duration::from_millis(5000)),
+            write_timeout: Some(Duration::from_millis(5000)),
+            call_timeout: Some(Duration::from_millis(30000)),
+        }
+    }
+}
+
+/// Pool of connections to a single address that can be shared between
+/// threads. Connections are multiplexed so a request picks the least busy
+/// one and several requests can be in flight on the same connection.
+pub(crate) struct Pool<S: Stream> {
+    addr: Address,
+    config: PoolConfig,
+    encodings: Vec<String>,
+    connector: S::Connector,
+    conns: Mutex<Conns<S>>,
+}
+
+/// Connections of a pool with the number of the ones being opened, which
+/// already count as part of the pool.
+struct Conns<S: Stream> {
+    open: Vec<Arc<Connection<S>>>,
+    connecting: usize,
+}
+
+impl<S: Stream> Pool<S> {
+    /// Create a pool of connections to the address. The encodings are
+    /// announced by every new connection.
+    pub(crate) fn new(
+        addr: Address,
+        config: PoolConfig,
+        encodings: Vec<String>,
+        connector: S::Connector,
+    ) -> Pool<S> {
+        Pool {
+            addr,
+            config,
+            encodings,
+            connector,
+            conns: Mutex::new(Conns {
+                open: Vec::new(),
+                connecting: 0,
+            }),
+        }
+    }
+
+    /// Return the deadline of a request, which is the call timeout from now
+    /// when the caller has none.
+    pub(crate) fn deadline(&self, deadline: Option<Instant>) -> Option<Instant> {
+        deadline.or_else(|| self.config.call_timeout.map(|timeout| Instant::now() + timeout))
+    }
+
+    /// Get a connection to the server. Closed and expired connections are
+    /// evicted first, then the least busy connection is returned unless it
+    /// is saturated and there is room for a new one. The pool is not locked
+    /// while a new connection is opened so that the other requests can still
+    /// use the existing ones.
+    pub(crate) fn checkout(&self) -> Result<Arc<Connection<S>>, Error> {
+        let reserved = {
+            let mut conns = self.conns.lock().unwrap();
+
+            let idle_timeout = self.config.idle_timeout;
+            conns.open.retain(|c| {
+                let expired = c.in_flight() == 0 && c.last_used().elapsed() >= idle_timeout;
+
+                !c.is_closed() && !expired
+            });
+
+            let full = conns.open.len() + conns.connecting >= self.config.max_idle;
+            let best = conns.open.iter().min_by_key(|c| c.in_flight()).cloned();
+
+            if let Some(conn) = best {
+                if conn.in_flight() < self.config.max_in_flight || full {
+                    return Ok(conn);
+                }
+            }
+
+            // The connection is not kept when the room left is taken by the
+            // ones being opened.
+            if !full {
+                conns.connecting += 1;
+            }
+
+            !full
+        };
+
+        let res = self.connect();
+
+        if reserved {
+            let mut conns = self.conns.lock().unwrap();
+            conns.connecting -= 1;
+
+            if let Ok(conn) = &res {
+                conns.open.push(Arc::clone(conn));
+            }
+        }
+
+        res
+    }
+
+    /// Open a new connection to the server.
+    fn connect(&self) -> Result<Arc<Connection<S>>, Error> {
+        let stream = S::connect(&self.addr, &self.connector, self.config.connect_timeout)?;
+
+        Ok(Arc::new(Connection::new(stream, self.encodings.clone(), &self.config)?))
+    }
+}
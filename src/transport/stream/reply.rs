@@ -0,0 +1,95 @@
+use super::super::super::Metadata;
+use super::metadata::{read_metadata, write_metadata};
+use super::Error;
+
+const STATUS_OK: u8 = 0;
+const STATUS_BUSY: u8 = 1;
+const STATUS_INTERNAL: u8 = 2;
+const STATUS_TOO_LARGE: u8 = 3;
+const STATUS_BAD_REQUEST: u8 = 4;
+
+/// Reply of the server to a request. It is written as a status followed by
+/// the body of the reply when there is one. The body of a processed request
+/// starts with the metadata of the reply.
+pub(crate) enum Reply {
+    /// The request has been processed and the body is the encoded reply
+    /// with the metadata attached by the handler.
+    Ok(Metadata, Vec<u8>),
+    /// The request has been rejected because the server is overloaded.
+    Busy,
+    /// The server failed to process the request, e.g. the implementation
+    /// panicked, for the given reason.
+    Internal(String),
+    /// The request has been rejected because it is larger than the maximum
+    /// size accepted by the server.
+    TooLarge,
+    /// The request can't be understood by the server, e.g. its message
+    /// can't be decoded, for the given reason.
+    BadRequest(String),
+}
+
+impl Reply {
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Reply::Ok(metadata, body) => {
+                let mut buf = Vec::with_capacity(1 + body.len());
+                buf.push(STATUS_OK);
+                write_metadata(&mut buf, metadata);
+                buf.extend_from_slice(body);
+
+                buf
+            }
+            Reply::Busy => vec![STATUS_BUSY],
+            Reply::TooLarge => vec![STATUS_TOO_LARGE],
+            Reply::Internal(reason) => with_reason(STATUS_INTERNAL, reason),
+            Reply::BadRequest(reason) => with_reason(STATUS_BAD_REQUEST, reason),
+        }
+    }
+
+    pub(crate) fn from_bytes(mut buf: Vec<u8>) -> Result<Reply, Error> {
+        match buf.first() {
+            Some(&STATUS_OK) => {
+                let mut rest = &buf[1..];
+                let metadata = read_metadata(&mut rest).ok_or(Error::InvalidReply)?;
+
+                Ok(Reply::Ok(metadata, rest.to_vec()))
+            }
+            Some(&STATUS_BUSY) => Ok(Reply::Busy),
+            Some(&STATUS_TOO_LARGE) => Ok(Reply::TooLarge),
+            Some(&STATUS_INTERNAL) => {
+                buf.remove(0);
+                String::from_utf8(buf)
+                    .map(Reply::Internal)
+                    .map_err(|_| Error::InvalidReply)
+            }
+            Some(&STATUS_BAD_REQUEST) => {
+                buf.remove(0);
+                String::from_utf8(buf)
+                    .map(Reply::BadRequest)
+                    .map_err(|_| Error::InvalidReply)
+            }
+            _ => Err(Error::InvalidReply),
+        }
+    }
+
+    /// Return the metadata and the body of the reply or the reason why
+    /// there is none.
+    pub(crate) fn into_body(self) -> Result<(Metadata, Vec<u8>), Error> {
+        match self {
+            Reply::Ok(metadata, body) => Ok((metadata, body)),
+            Reply::Busy => Err(Error::ServerBusy),
+            Reply::Internal(reason) => Err(Error::Internal(reason)),
+            Reply::TooLarge => Err(Error::MessageTooLarge),
+            Reply::BadRequest(reason) => Err(Error::BadRequest(reason)),
+        }
+    }
+}
+
+/// Write the status followed by the reason of the failure.
+fn with_reason(status: u8, reason: &str) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(1 + reason.len());
+    buf.push(status);
+    buf.extend_from_slice(reason.as_bytes());
+
+    buf
+}
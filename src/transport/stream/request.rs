@@ -0,0 +1,70 @@
+use super::super::super::Metadata;
+use super::metadata::{read_metadata, write_metadata};
+use super::Error;
+use std::time::{Duration, Instant};
+
+/// Number of bytes of the timeout that starts every request.
+const TIMEOUT_SIZE: usize = 8;
+
+/// Request of a client. It is written as the time left to the caller to
+/// wait for the reply, in milliseconds or zero when there is no deadline,
+/// then the metadata of the request, followed by the encoded message.
+pub(crate) struct Request {
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) metadata: Metadata,
+    pub(crate) body: Vec<u8>,
+}
+
+impl Request {
+    /// Create the request of a caller waiting for the reply until the
+    /// deadline. The deadline is sent as a timeout as the clocks of the
+    /// peers are not the same.
+    pub(crate) fn new(body: Vec<u8>, deadline: Option<Instant>, metadata: Metadata) -> Request {
+        Request {
+            timeout: deadline.map(|deadline| deadline.saturating_duration_since(Instant::now())),
+            metadata,
+            body,
+        }
+    }
+
+    /// Return the deadline of the request received at the given instant.
+    pub(crate) fn deadline(&self, received: Instant) -> Option<Instant> {
+        self.timeout.map(|timeout| received + timeout)
+    }
+
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(TIMEOUT_SIZE + self.body.len());
+
+        // A deadline about to elapse must not be mistaken for no deadline.
+        let millis = match self.timeout {
+            Some(timeout) => (timeout.as_millis() as u64).max(1),
+            None => 0,
+        };
+
+        buf.extend_from_slice(&millis.to_be_bytes());
+        write_metadata(&mut buf, &self.metadata);
+        buf.extend_from_slice(&self.body);
+
+        buf
+    }
+
+    pub(crate) fn from_bytes(buf: Vec<u8>) -> Result<Request, Error> {
+        if buf.len() < TIMEOUT_SIZE {
+            return Err(Error::InvalidRequest);
+        }
+
+        let (millis, mut rest) = buf.split_at(TIMEOUT_SIZE);
+
+        let mut bytes = [0u8; TIMEOUT_SIZE];
+        bytes.copy_from_slice(millis);
+
+        let timeout = match u64::from_be_bytes(bytes) {
+            0 => None,
+            millis => Some(Duration::from_millis(millis)),
+        };
+        let metadata = read_metadata(&mut rest).ok_or(Error::InvalidRequest)?;
+        let body = rest.to_vec();
+
+        Ok(Request { timeout, metadata, body })
+    }
+}
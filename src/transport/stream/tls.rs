@@ -0,0 +1,210 @@
+use super::super::super::{group::Address, PeerIdentity};
+use super::super::tls::TlsClientConfig;
+use super::{Error, ServeOptions, Stream};
+use rustls::{ClientConnection, Connection, ServerConfig, ServerConnection, ServerName};
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, Shutdown, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use x509_parser::prelude::{FromDer, GeneralName, X509Certificate};
+
+/// Stream encrypted with TLS on top of a TCP connection. The state of the
+/// session is shared by the clones so that one thread can read while
+/// another one is writing.
+pub struct TlsStream {
+    conn: Arc<Mutex<Connection>>,
+    socket: TcpStream,
+    peer_identity: Option<Arc<PeerIdentity>>,
+}
+
+impl TlsStream {
+    /// Run the server side of the TLS handshake over a connection that has
+    /// just been accepted, bounded by the timeouts of the server.
+    pub(crate) fn accept(socket: TcpStream, config: Arc<ServerConfig>, opts: &ServeOptions) -> Result<TlsStream, Error> {
+        // A client that never completes the handshake must not hold the
+        // connection forever.
+        socket.set_read_timeout(opts.read_timeout)?;
+        socket.set_write_timeout(opts.write_timeout)?;
+
+        let conn = ServerConnection::new(config).map_err(tls_error)?;
+
+        TlsStream::handshake(socket, Connection::from(conn))
+    }
+
+    fn handshake(mut socket: TcpStream, mut conn: Connection) -> Result<TlsStream, Error> {
+        while conn.is_handshaking() {
+            conn.complete_io(&mut socket)?;
+        }
+
+        let peer_identity = match conn.peer_certificates() {
+            Some(certs) => {
+                let certs = certs.iter().map(|cert| cert.0.clone()).collect();
+                Some(Arc::new(read_identity(certs)?))
+            }
+            None => None,
+        };
+
+        Ok(TlsStream {
+            conn: Arc::new(Mutex::new(conn)),
+            socket,
+            peer_identity,
+        })
+    }
+
+    /// Write the records waiting to be sent to the peer.
+    fn flush_tls(&self, conn: &mut Connection) -> io::Result<()> {
+        while conn.wants_write() {
+            conn.write_tls(&mut &self.socket)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Read for TlsStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            match self.conn.lock().unwrap().reader().read(buf) {
+                // No plaintext is available until more records are received.
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => (),
+                res => return res,
+            }
+
+            // Wait for the peer without holding the session so that the
+            // writers are not blocked meanwhile. The read timeout of the
+            // socket applies.
+            self.socket.peek(&mut [0u8; 1])?;
+
+            let mut conn = self.conn.lock().unwrap();
+            conn.read_tls(&mut &self.socket)?;
+            conn.process_new_packets().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            // Alerts might have to be sent back.
+            self.flush_tls(&mut conn)?;
+        }
+    }
+}
+
+impl Write for TlsStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut conn = self.conn.lock().unwrap();
+        let n = conn.writer().write(buf)?;
+        self.flush_tls(&mut conn)?;
+
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        conn.writer().flush()?;
+        self.flush_tls(&mut conn)
+    }
+}
+
+impl Stream for TlsStream {
+    type Connector = TlsClientConfig;
+
+    fn connect(addr: &Address, connector: &TlsClientConfig, timeout: Option<Duration>) -> Result<Self, Error> {
+        let socket_addr = match addr.get_socket_addr() {
+            Some(addr) => addr,
+            None => return Err(Error::NoSocketAddress),
+        };
+
+        // The certificate of the server is checked against the IP address
+        // unless a name is configured.
+        let server_name = match connector.server_name.clone() {
+            Some(name) => name,
+            None => ServerName::IpAddress(socket_addr.ip()),
+        };
+
+        let socket = match timeout {
+            Some(timeout) => TcpStream::connect_timeout(&socket_addr, timeout)?,
+            None => TcpStream::connect(socket_addr)?,
+        };
+        // The TLS handshake is bounded by the same timeout as the connection.
+        socket.set_read_timeout(timeout)?;
+        socket.set_write_timeout(timeout)?;
+
+        let conn = ClientConnection::new(Arc::clone(&connector.inner), server_name).map_err(tls_error)?;
+        let stream = TlsStream::handshake(socket, Connection::from(conn))?;
+
+        // Replies can take longer than the handshake.
+        stream.socket.set_read_timeout(None)?;
+        stream.socket.set_write_timeout(None)?;
+
+        Ok(stream)
+    }
+
+    fn try_clone(&self) -> io::Result<Self> {
+        Ok(TlsStream {
+            conn: Arc::clone(&self.conn),
+            socket: self.socket.try_clone()?,
+            peer_identity: self.peer_identity.clone(),
+        })
+    }
+
+    fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        // Nothing will be written anymore unless only the reading side is
+        // closed.
+        if how != Shutdown::Read {
+            if let Ok(mut conn) = self.conn.lock() {
+                conn.send_close_notify();
+                // The peer might be gone already.
+                self.flush_tls(&mut conn).ok();
+            }
+        }
+
+        self.socket.shutdown(how)
+    }
+
+    fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.socket.set_read_timeout(dur)
+    }
+
+    fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.socket.set_write_timeout(dur)
+    }
+
+    fn peer_identity(&self) -> Option<Arc<PeerIdentity>> {
+        self.peer_identity.clone()
+    }
+}
+
+fn tls_error(err: rustls::Error) -> Error {
+    Error::from(io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+fn invalid_certificate<E>(_: E) -> Error {
+    Error::from(io::Error::new(io::ErrorKind::InvalidData, "invalid peer certificate"))
+}
+
+/// Extract the subject and the alternative names from the certificate of
+/// the peer, which has been verified during the handshake.
+fn read_identity(certs: Vec<Vec<u8>>) -> Result<PeerIdentity, Error> {
+    let (_, cert) = X509Certificate::from_der(&certs[0]).map_err(invalid_certificate)?;
+
+    let mut alt_names = Vec::new();
+    if let Some(ext) = cert.subject_alternative_name().map_err(invalid_certificate)? {
+        for name in &ext.value.general_names {
+            match name {
+                GeneralName::DNSName(v) | GeneralName::RFC822Name(v) | GeneralName::URI(v) => {
+                    alt_names.push(v.to_string())
+                }
+                GeneralName::IPAddress(v) => {
+                    let ip = match v.len() {
+                        4 => IpAddr::from(<[u8; 4]>::try_from(*v).unwrap()),
+                        16 => IpAddr::from(<[u8; 16]>::try_from(*v).unwrap()),
+                        _ => continue,
+                    };
+                    alt_names.push(ip.to_string());
+                }
+                // Other kinds of names are not used to identify clients.
+                _ => (),
+            }
+        }
+    }
+
+    let subject = cert.subject().to_string();
+
+    Ok(PeerIdentity::new(certs, subject, alt_names))
+}
@@ -0,0 +1,296 @@
+use super::super::super::{
+    executor::{Executor, ThreadPool},
+    group::Address,
+    metrics::{MetricsSink, Recorder},
+    CallOptions,
+    Metadata,
+    RequestProcessor,
+};
+use super::super::{ClientTransport, ServerTransport};
+use super::super::codec::{Codec, JsonCodec};
+use super::handshake::encodings;
+use super::{serve_connection, Connections, Error, Overflow, Pool, PoolConfig, ServeOptions, Stream, WAIT_TIMEOUT};
+use mio::{Events, Poll};
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use std::io;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::thread::Builder;
+use std::time::{Duration, Instant};
+
+/// A stream that a server can accept from the clients. Each transport only
+/// tells how to bind to its address and how to accept the connections, the
+/// rest of the server being shared.
+pub trait Accept: Stream {
+    /// Socket listening to the connections of the clients.
+    type Listener: Send;
+    /// Settings required to open a stream out of an accepted connection.
+    type Acceptor: Clone + Send + 'static;
+    /// Connection accepted by the listener before it is opened.
+    type Incoming: Send + 'static;
+
+    /// Bind to the address and register the listener to the poll so that it
+    /// wakes up when a client connects. The address actually bound is
+    /// returned as the OS can choose a part of it.
+    fn bind(addr: &Address, poll: &Poll) -> Result<(Self::Listener, Address), Error>;
+
+    /// Accept a connection without blocking, or fail with WouldBlock when no
+    /// client is waiting. The address of the client is returned with it.
+    fn accept(listener: &Self::Listener) -> io::Result<(Self::Incoming, Address)>;
+
+    /// Open the stream of an accepted connection. It runs in the thread of
+    /// the connection so that a slow client doesn't hold the others.
+    fn open(incoming: Self::Incoming, acceptor: &Self::Acceptor, opts: &ServeOptions) -> Result<Self, Error>;
+
+    /// Stop listening so that new connections are refused.
+    fn close(listener: Self::Listener, addr: &Address, poll: &Poll) -> io::Result<()>;
+}
+
+/// ServerTransport implementation shared by the transports over a stream.
+/// Each connection is read by a thread of its own and the requests are
+/// processed by the executor, which is a ThreadPool by default. The
+/// messages are serialized with the codec, which is JSON by default.
+pub struct StreamServerTransport<S: Accept, C = JsonCodec, E = ThreadPool> {
+    addr: Address,
+    acceptor: S::Acceptor,
+    socket: Option<S::Listener>,
+    conns: Arc<Connections<S>>,
+    pool: Arc<E>,
+    opts: ServeOptions,
+    wait_timeout: Option<Duration>,
+    poll: Poll,
+    events: Events,
+    codec: PhantomData<C>,
+}
+
+impl<S: Accept, C: Codec, E: Executor> StreamServerTransport<S, C, E> {
+    /// Create a transport object that will bind to the address and open the
+    /// connections with the acceptor.
+    pub(crate) fn with_acceptor(addr: Address, acceptor: S::Acceptor, executor: E, overflow: Overflow) -> io::Result<Self> {
+        let poll = Poll::new()?;
+
+        Ok(StreamServerTransport {
+            addr,
+            acceptor,
+            socket: None,
+            conns: Arc::new(Connections::new()),
+            pool: Arc::new(executor),
+            opts: ServeOptions::new(overflow),
+            wait_timeout: WAIT_TIMEOUT,
+            poll,
+            events: Events::with_capacity(1),
+            codec: PhantomData,
+        })
+    }
+
+    /// Set the size in bytes of the largest request accepted by the server,
+    /// which is 16 MiB by default. A larger request is discarded without
+    /// being buffered and the client is answered with MessageTooLarge.
+    pub fn with_max_message_size(mut self, max: usize) -> Self {
+        self.opts.max_message_size = max;
+        self
+    }
+
+    /// Set the number of connections served at the same time, which is
+    /// 1024 by default. Each of them is read by a thread of its own and the
+    /// connections above the limit are closed as soon as accepted.
+    pub fn with_max_connections(mut self, max: usize) -> Self {
+        self.opts.max_connections = max;
+        self
+    }
+
+    /// Set the duration after which a connection without requests is
+    /// closed, which is 5 seconds by default, or None to keep it open. It
+    /// also bounds each step of the TLS handshake.
+    pub fn with_read_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.opts.read_timeout = timeout;
+        self
+    }
+
+    /// Set the duration after which writing a reply fails, which is 5
+    /// seconds by default, or None to wait as long as needed.
+    pub fn with_write_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.opts.write_timeout = timeout;
+        self
+    }
+
+    /// Set how long the server waits for a connection before checking if
+    /// it has been asked to shut down, which is 100 milliseconds by default.
+    pub fn with_wait_timeout(mut self, timeout: Duration) -> Self {
+        self.wait_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the sink receiving the measurements of the server instead of the
+    /// sink of the process.
+    pub fn with_metrics(mut self, sink: Arc<dyn MetricsSink>) -> Self {
+        self.opts.metrics = Recorder::new(sink);
+        self
+    }
+}
+
+impl<Req, Rep, S, C, E> ServerTransport<Req, Rep> for StreamServerTransport<S, C, E>
+where
+    for<'de> Req: Debug + Deserialize<'de> + Send + 'static,
+    Rep: Debug + Serialize + 'static,
+    S: Accept,
+    C: Codec,
+    E: Executor,
+{
+    type Error = Error;
+
+    /// Get the address of the server.
+    fn get_addr(&self) -> Address {
+        self.addr.clone()
+    }
+
+    /// Try to bind to the address and set the socket if successfull,
+    /// otherwise the result contains the error.
+    fn connect(&mut self) -> Result<(), Error> {
+        let (socket, addr) = S::bind(&self.addr, &self.poll)?;
+
+        self.addr = addr;
+        self.socket = Some(socket);
+
+        Ok(())
+    }
+
+    /// Wait for a connection request and start a thread that will open the
+    /// stream, then read the incoming requests and write the replies to it.
+    fn next(&mut self, f: Arc<Box<RequestProcessor<Req, Rep>>>) -> Result<(), Error> {
+        let socket = match self.socket.as_ref() {
+            Some(socket) => socket,
+            None => return Err(Error::NotRunning),
+        };
+
+        let (incoming, in_addr) = match S::accept(socket) {
+            Ok(v) => v,
+            Err(e) => {
+                // A WouldBlock error only means no connection yet
+                // so we need to wait a bit.
+                if e.kind() == io::ErrorKind::WouldBlock {
+                    // Wait for an IO event from the OS.
+                    self.poll.poll(&mut self.events, self.wait_timeout)?;
+                    // As the next is looped, we simply return to try
+                    // a new accept.
+                    return Ok(());
+                }
+
+                return Err(Error::from(e));
+            }
+        };
+
+        // The connection is closed right away when the server is already
+        // serving as many as it can.
+        let slot = match Connections::acquire(&self.conns, self.opts.max_connections) {
+            Some(slot) => slot,
+            None => {
+                tracing::warn!(peer = %in_addr, max = self.opts.max_connections, "too many connections");
+                return Ok(());
+            }
+        };
+
+        let out_addr = self.addr.clone();
+        let acceptor = self.acceptor.clone();
+        let conns = Arc::clone(&self.conns);
+        let pool = Arc::clone(&self.pool);
+        let opts = self.opts.clone();
+
+        Builder::new()
+            .name(format!("server-connection-{}", in_addr))
+            .spawn(move || {
+                let _slot = slot;
+                let peer = in_addr.clone();
+
+                let res = S::open(incoming, &acceptor, &opts).and_then(|stream| {
+                    serve_connection::<_, _, _, _, C>(stream, conns, pool, opts, f, out_addr, in_addr)
+                });
+
+                if let Err(e) = res {
+                    tracing::warn!(peer = %peer, error = %e, "connection failed");
+                }
+            })?;
+
+        Ok(())
+    }
+
+    /// Close the listener so that new connections are refused, then let
+    /// the requests in progress complete until the deadline.
+    fn shutdown(&mut self, deadline: Instant) -> Result<usize, Error> {
+        // The requests in progress are drained even when the listener can't
+        // be closed cleanly.
+        let res = self.close();
+        let pending = self.conns.drain(&*self.pool, deadline);
+        res?;
+
+        Ok(pending)
+    }
+}
+
+impl<S: Accept, C, E> StreamServerTransport<S, C, E> {
+    fn close(&mut self) -> io::Result<()> {
+        match self.socket.take() {
+            Some(socket) => S::close(socket, &self.addr, &self.poll),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<S: Accept, C, E> Drop for StreamServerTransport<S, C, E> {
+    fn drop(&mut self) {
+        self.close().ok();
+    }
+}
+
+/// ClientTransport implementation shared by the transports over a stream.
+/// Connections are kept in a pool and shared by the concurrent requests.
+/// Messages are serialized with the codec which is JSON by default.
+pub struct StreamClientTransport<S: Stream, C = JsonCodec> {
+    pool: Pool<S>,
+    codec: PhantomData<C>,
+}
+
+impl<S: Stream, C: Codec> StreamClientTransport<S, C> {
+    /// Create a client transport that will open the connections to the
+    /// address with the connector.
+    pub(crate) fn with_connector(addr: Address, config: PoolConfig, connector: S::Connector) -> Self {
+        StreamClientTransport {
+            pool: Pool::new(addr, config, encodings::<C>(), connector),
+            codec: PhantomData,
+        }
+    }
+}
+
+impl<Req, Rep, S, C> ClientTransport<Req, Rep> for StreamClientTransport<S, C>
+where
+    for<'de> Rep: Deserialize<'de>,
+    Req: Serialize,
+    S: Stream,
+    C: Codec,
+{
+    type Error = Error;
+
+    /// Send the message to the server over a connection of the pool and
+    /// wait for the reply. Other requests can use the same connection
+    /// meanwhile.
+    fn send(&self, msg: Req) -> Result<Rep, Error> {
+        self.send_with_deadline(msg, None)
+    }
+
+    /// Send the message like send but give up when the deadline elapses.
+    fn send_with_deadline(&self, msg: Req, deadline: Option<Instant>) -> Result<Rep, Error> {
+        let opts = CallOptions {
+            deadline,
+            ..CallOptions::default()
+        };
+
+        self.send_with_options(msg, &opts).map(|(rep, _)| rep)
+    }
+
+    /// Send the message with the metadata of the options and return the
+    /// reply with the metadata of the server.
+    fn send_with_options(&self, msg: Req, opts: &CallOptions) -> Result<(Rep, Metadata), Error> {
+        super::send::<_, C, _, _>(&self.pool, msg, opts)
+    }
+}
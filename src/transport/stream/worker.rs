@@ -0,0 +1,61 @@
+use super::super::super::executor::ThreadPool;
+use std::io;
+
+/// Behavior of a server when a request comes while the queue of the pool
+/// of workers is full.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Overflow {
+    /// Stop reading the requests of the connection until there is room in
+    /// the queue. Only this connection waits: the server keeps accepting new
+    /// ones, which wait in turn as soon as they send a request, so that
+    /// there is at most one request waiting per connection.
+    Block,
+    /// Answer the client that the server is busy.
+    Reject,
+    /// Drop the request without answering. The other requests of the
+    /// connection are still served, and the client only learns about it
+    /// when its deadline elapses. The clients of such a server must not
+    /// set PoolConfig::call_timeout to None unless they call with a
+    /// deadline, otherwise they wait for the reply forever.
+    Drop,
+}
+
+/// Configuration of the pool of workers processing the requests of a
+/// server.
+#[derive(Clone, Debug)]
+pub struct WorkerConfig {
+    /// Number of threads processing the requests.
+    pub workers: usize,
+    /// Maximum number of requests waiting for a worker, or None to let the
+    /// queue grow without limit.
+    pub max_queued: Option<usize>,
+    /// Behavior when a request comes while the queue is full.
+    pub overflow: Overflow,
+}
+
+impl Default for WorkerConfig {
+    fn default() -> Self {
+        WorkerConfig {
+            workers: 4,
+            max_queued: None,
+            overflow: Overflow::Block,
+        }
+    }
+}
+
+impl WorkerConfig {
+    /// Create the pool of workers described by the configuration. A pool
+    /// without workers, or whose queue can't hold a single request, would
+    /// never process any so the configuration is refused.
+    pub(crate) fn pool(&self) -> io::Result<ThreadPool> {
+        if self.workers == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "pool needs at least one worker"));
+        }
+
+        match self.max_queued {
+            Some(0) => Err(io::Error::new(io::ErrorKind::InvalidInput, "queue needs room for at least one request")),
+            Some(max) => Ok(ThreadPool::with_max_queued(self.workers, max)),
+            None => Ok(ThreadPool::new(self.workers)),
+        }
+    }
+}
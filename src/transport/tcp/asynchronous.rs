@@ -0,0 +1,670 @@
+use super::super::super::{
+    group::Address,
+    metrics::{MetricsSink, Recorder},
+    CallOptions,
+    Context,
+    Metadata,
+};
+use super::super::codec::{decode_with, encode_with, Codec, JsonCodec};
+use super::super::{panic_message, AsyncClientTransport, AsyncRequestProcessor, AsyncServerTransport};
+use super::super::stream::handshake::{self, encodings, Hello};
+use super::super::stream::pending::{Pending, ReplyResult};
+use super::super::stream::reply::Reply;
+use super::super::stream::request::Request;
+use super::super::stream::{
+    check_frame_size,
+    closed_error,
+    deadline_error,
+    decode_header,
+    decode_request,
+    encode_frame,
+    CancelOnDrop,
+    Error,
+    PoolConfig,
+    FRAME_HEADER_SIZE,
+    MAX_MESSAGE_SIZE,
+    READ_TIMEOUT,
+    WRITE_TIMEOUT,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::future::Future;
+use std::io;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{oneshot, Mutex as AsyncMutex};
+use tokio::task::JoinHandle;
+
+/// Run the operation until the timeout elapses, when there is one, in
+/// which case it fails with TimedOut.
+async fn with_timeout<T, E, F>(timeout: Option<Duration>, fut: F) -> Result<T, E>
+where
+    E: From<io::Error>,
+    F: Future<Output = Result<T, E>>,
+{
+    match timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, fut).await {
+            Ok(res) => res,
+            Err(_) => Err(E::from(io::ErrorKind::TimedOut.into())),
+        },
+        None => fut.await,
+    }
+}
+
+/// Asynchronous counterpart of the frame writer. The wire format is the
+/// same so that blocking and asynchronous peers can talk to each other.
+async fn write_frame<W: AsyncWrite + Unpin>(w: &mut W, id: u64, buf: &[u8]) -> io::Result<()> {
+    w.write_all(&encode_frame(id, buf)?[..]).await?;
+    w.flush().await
+}
+
+/// Asynchronous counterpart of the header reader. None is returned when
+/// the peer closed the connection in between two frames.
+async fn read_header<R: AsyncRead + Unpin>(r: &mut R) -> io::Result<Option<(usize, u64)>> {
+    let mut header = [0u8; FRAME_HEADER_SIZE];
+    let mut n = 0;
+
+    while n < FRAME_HEADER_SIZE {
+        match r.read(&mut header[n..]).await? {
+            0 if n == 0 => return Ok(None),
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            k => n += k,
+        }
+    }
+
+    Ok(Some(decode_header(&header)))
+}
+
+/// Read the payload of the frame whose header has just been read.
+async fn read_payload<R: AsyncRead + Unpin>(r: &mut R, len: usize) -> io::Result<Vec<u8>> {
+    let mut buf = vec![0u8; len];
+    r.read_exact(&mut buf[..]).await?;
+
+    Ok(buf)
+}
+
+/// Discard the payload of the frame whose header has just been read so
+/// that the next frame can be read, without keeping it in memory.
+async fn skip_payload<R: AsyncRead + Unpin>(r: &mut R, len: usize) -> io::Result<()> {
+    let n = tokio::io::copy(&mut r.take(len as u64), &mut tokio::io::sink()).await?;
+    if n < len as u64 {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    Ok(())
+}
+
+/// Asynchronous counterpart of the frame reader. The payload is not read
+/// when it is larger than the maximum size.
+async fn read_frame<R: AsyncRead + Unpin>(r: &mut R, max_size: usize) -> Result<Option<(u64, Vec<u8>)>, Error> {
+    let (len, id) = match read_header(r).await? {
+        Some(header) => header,
+        None => return Ok(None),
+    };
+
+    check_frame_size(len, max_size)?;
+
+    Ok(Some((id, read_payload(r, len).await?)))
+}
+
+/// Settings of the connections accepted by an asynchronous server.
+#[derive(Clone, Copy, Debug)]
+struct ServeOptions {
+    /// Size in bytes of the largest request accepted.
+    max_message_size: usize,
+    /// Duration after which a connection without requests is closed.
+    read_timeout: Option<Duration>,
+    /// Duration after which writing a reply fails.
+    write_timeout: Option<Duration>,
+}
+
+/// AsyncServerTransport implementation over TCP using the tokio runtime
+/// and the codec to serialize the messages, which is JSON by default.
+pub struct AsyncTcpServerTransport<C = JsonCodec> {
+    addr: Address,
+    socket: Option<TcpListener>,
+    opts: ServeOptions,
+    metrics: Recorder,
+    codec: PhantomData<C>,
+}
+
+impl AsyncTcpServerTransport {
+    /// Create a transport object. The socket will be bind to
+    /// the given address.
+    pub fn new(addr: Address) -> AsyncTcpServerTransport {
+        AsyncTcpServerTransport::with_codec(addr)
+    }
+}
+
+impl<C: Codec> AsyncTcpServerTransport<C> {
+    /// Create a transport object that will use the codec given as type
+    /// parameter to serialize the messages.
+    pub fn with_codec(addr: Address) -> AsyncTcpServerTransport<C> {
+        AsyncTcpServerTransport {
+            addr,
+            socket: None,
+            opts: ServeOptions {
+                max_message_size: MAX_MESSAGE_SIZE,
+                read_timeout: READ_TIMEOUT,
+                write_timeout: WRITE_TIMEOUT,
+            },
+            metrics: Recorder::default(),
+            codec: PhantomData,
+        }
+    }
+
+    /// Set the size in bytes of the largest request accepted. Larger ones
+    /// are rejected without being read in memory.
+    pub fn with_max_message_size(mut self, max: usize) -> Self {
+        self.opts.max_message_size = max;
+        self
+    }
+
+    /// Set the duration after which a connection without requests is
+    /// closed, which is 5 seconds by default, or None to keep it open. It
+    /// also bounds the handshake.
+    pub fn with_read_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.opts.read_timeout = timeout;
+        self
+    }
+
+    /// Set the duration after which writing a reply fails, which is 5
+    /// seconds by default, or None to wait as long as needed.
+    pub fn with_write_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.opts.write_timeout = timeout;
+        self
+    }
+
+    /// Set the sink receiving the measurements of the server instead of the
+    /// sink of the process.
+    pub fn with_metrics(mut self, sink: Arc<dyn MetricsSink>) -> Self {
+        self.metrics = Recorder::new(sink);
+        self
+    }
+}
+
+impl<Req, Rep, C> AsyncServerTransport<Req, Rep> for AsyncTcpServerTransport<C>
+where
+    for<'de> Req: Deserialize<'de> + Send + 'static,
+    Rep: Serialize + Send + 'static,
+    C: Codec,
+{
+    type Error = Error;
+
+    /// Get the socket address of the server.
+    fn get_addr(&self) -> Address {
+        self.addr.clone()
+    }
+
+    /// Try to bind to the socket address and set the socket if
+    /// successfull, otherwise the result contains the error.
+    async fn connect(&mut self) -> Result<(), Error> {
+        let socket_addr = match self.addr.get_socket_addr() {
+            Some(addr) => addr,
+            None => return Err(Error::NoSocketAddress),
+        };
+
+        let socket = TcpListener::bind(socket_addr).await?;
+        // The port is chosen by the OS when the address asks for port 0.
+        self.addr = Address::Socket(socket.local_addr()?);
+
+        self.socket = Some(socket);
+
+        Ok(())
+    }
+
+    /// Wait for a connection request and spawn a task that will read
+    /// the incoming requests and write the replies to the stream.
+    async fn next(&mut self, f: Arc<Box<AsyncRequestProcessor<Req, Rep>>>) -> Result<(), Error> {
+        let socket = match self.socket.as_ref() {
+            Some(socket) => socket,
+            None => return Err(Error::NotRunning),
+        };
+
+        let (stream, sock_addr) = socket.accept().await?;
+
+        let ctx = Context::new(Address::Socket(sock_addr), self.addr.clone())
+            .with_metrics(self.metrics.clone());
+        let opts = self.opts;
+
+        tokio::spawn(async move {
+            if let Err(e) = serve_connection::<_, _, C>(stream, f, ctx, opts).await {
+                tracing::warn!(peer = %sock_addr, error = %e, "connection failed");
+            }
+        });
+
+        Ok(())
+    }
+}
+
+/// Read the handshake of the client and answer with the encoding that will
+/// be used by the connection, or with the reason of the rejection.
+async fn accept_handshake<C: Codec>(stream: &mut TcpStream, max_size: usize) -> Result<String, Error> {
+    let welcome = handshake::accept::<C>(read_frame(stream, max_size).await?)?;
+
+    write_frame(stream, 0, &welcome.to_bytes()?).await?;
+
+    welcome.into_encoding()
+}
+
+/// Read the requests coming from a connection until it is closed. Each
+/// request is processed in its own task and the replies are written back
+/// as soon as they are ready. Requests larger than the maximum size are
+/// rejected.
+async fn serve_connection<Req, Rep, C>(
+    mut stream: TcpStream,
+    f: Arc<Box<AsyncRequestProcessor<Req, Rep>>>,
+    ctx: Context,
+    opts: ServeOptions,
+) -> Result<(), Error>
+where
+    Req: DeserializeOwned + Send + 'static,
+    Rep: Serialize + Send + 'static,
+    C: Codec,
+{
+    let max_size = opts.max_message_size;
+    let encoding = with_timeout(opts.read_timeout, accept_handshake::<C>(&mut stream, max_size)).await?;
+    let encoding = Arc::new(encoding);
+
+    let (reader, writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    let writer = Arc::new(Writer {
+        half: AsyncMutex::new(writer),
+        metrics: ctx.get_metrics().clone(),
+        server: ctx.get_out_addr().to_string(),
+        timeout: opts.write_timeout,
+    });
+    writer.metrics.record(|m| m.connection_accepted(&writer.server));
+
+    // The requests in progress are cancelled when the client is gone.
+    let mut cancel = CancelOnDrop::new();
+    let ctx = ctx.with_cancellation(cancel.token());
+
+    loop {
+        // Waiting for the next frame to be buffered can be given up without
+        // losing any byte of it.
+        let idle = with_timeout(opts.read_timeout, async { reader.fill_buf().await.map(|buf| buf.is_empty()) });
+
+        match idle.await {
+            Ok(false) => (),
+            // The client closed the connection.
+            Ok(true) => return Ok(()),
+            // The client is still waiting for replies, held by the requests
+            // in progress, so it might send more requests.
+            Err(ref e) if e.kind() == io::ErrorKind::TimedOut && Arc::strong_count(&writer) > 1 => continue,
+            // The connection has been idle for too long.
+            Err(ref e) if e.kind() == io::ErrorKind::TimedOut => {
+                cancel.disarm();
+                return Ok(());
+            }
+            Err(e) => return Err(Error::from(e)),
+        }
+
+        // The rest of the frame is expected to follow without delay.
+        let (len, id) = match with_timeout(opts.read_timeout, read_header(&mut reader)).await? {
+            Some(header) => header,
+            None => return Ok(()),
+        };
+
+        if len > max_size {
+            tracing::warn!(id, peer = %ctx.get_in_addr(), len, max = max_size, "request is too large");
+
+            // The connection can still be used for the next requests.
+            with_timeout(opts.read_timeout, skip_payload(&mut reader, len)).await?;
+            writer.reply(id, &Reply::TooLarge).await?;
+            continue;
+        }
+
+        let buf = with_timeout(opts.read_timeout, read_payload(&mut reader, len)).await?;
+        writer.metrics.record(|m| m.bytes_received(&writer.server, FRAME_HEADER_SIZE + buf.len()));
+        // A request that can't be understood only fails itself.
+        let (request, req): (_, Req) = match decode_request::<C, _>(buf, &encoding) {
+            Ok(v) => v,
+            Err(e) => {
+                tracing::warn!(id, peer = %ctx.get_in_addr(), error = %e, "invalid request");
+
+                writer.reply(id, &Reply::BadRequest(e.to_string())).await?;
+                continue;
+            }
+        };
+
+        let ctx = ctx
+            .clone()
+            .with_deadline(request.deadline(Instant::now()))
+            .with_metadata(request.metadata);
+        // The handler attaches the metadata of the reply to its own clone
+        // of the context.
+        let reply_ctx = ctx.clone();
+        let fut = f(req, ctx);
+        let writer = Arc::clone(&writer);
+        let encoding = Arc::clone(&encoding);
+
+        tokio::spawn(async move {
+            // The processor runs in a task of its own so that a panic is
+            // caught by the runtime and reported to the client.
+            let out = match tokio::spawn(fut).await {
+                Ok(reply) => encode_with::<C, _>(&encoding, &reply)
+                    .map(|out| Reply::Ok(reply_ctx.take_reply_metadata(), out)),
+                Err(e) if e.is_panic() => {
+                    let msg = panic_message(&*e.into_panic());
+                    tracing::error!(id, panic = %msg, "request panicked");
+
+                    Ok(Reply::Internal(msg))
+                }
+                Err(e) => {
+                    // The reply will never come so the connection is closed
+                    // to let the client know.
+                    writer.half.lock().await.shutdown().await.ok();
+                    tracing::warn!(id, error = %e, "request failed");
+                    return;
+                }
+            };
+
+            let res = match out {
+                Ok(out) => writer.reply(id, &out).await,
+                Err(e) => Err(io::Error::from(e)),
+            };
+
+            if let Err(e) = res {
+                tracing::warn!(id, error = %e, "request failed");
+            }
+        });
+    }
+}
+
+/// Write half of a connection shared by the requests in progress, which
+/// hold it until their reply is written.
+struct Writer {
+    half: AsyncMutex<OwnedWriteHalf>,
+    metrics: Recorder,
+    /// Address of the server, which labels its measurements.
+    server: String,
+    timeout: Option<Duration>,
+}
+
+impl Writer {
+    /// Write the reply of the request with the given identifier.
+    async fn reply(&self, id: u64, reply: &Reply) -> io::Result<()> {
+        let buf = reply.to_bytes();
+
+        with_timeout(self.timeout, async { write_frame(&mut *self.half.lock().await, id, &buf[..]).await }).await?;
+        self.metrics.record(|m| m.bytes_sent(&self.server, FRAME_HEADER_SIZE + buf.len()));
+
+        Ok(())
+    }
+}
+
+/// Settings of the connection of an asynchronous client.
+#[derive(Clone, Copy, Debug)]
+struct ConnectOptions {
+    /// Size in bytes of the largest reply accepted.
+    max_message_size: usize,
+    /// Duration after which opening the connection and negotiating the
+    /// protocol fails.
+    connect_timeout: Option<Duration>,
+    /// Duration after which writing a request fails.
+    write_timeout: Option<Duration>,
+    /// Duration after which a request without a deadline of its own fails.
+    call_timeout: Option<Duration>,
+    /// Duration after which an unused connection is opened again.
+    idle_timeout: Duration,
+}
+
+/// Connection to a server shared by the concurrent requests of a client.
+struct AsyncConnection {
+    encoding: String,
+    writer: AsyncMutex<OwnedWriteHalf>,
+    write_timeout: Option<Duration>,
+    pending: Arc<Pending<oneshot::Sender<ReplyResult>>>,
+    last_used: Mutex<Instant>,
+    reader: JoinHandle<()>,
+}
+
+impl AsyncConnection {
+    /// Open a connection to the address, negotiate the version and the
+    /// encoding with the server and spawn the task that reads the replies.
+    /// Replies larger than the maximum size are discarded.
+    async fn open(addr: &Address, encodings: Vec<String>, opts: &ConnectOptions) -> Result<AsyncConnection, Error> {
+        let socket_addr = match addr.get_socket_addr() {
+            Some(addr) => addr,
+            None => return Err(Error::NoSocketAddress),
+        };
+        let max_size = opts.max_message_size;
+
+        // The server is expected to answer the handshake as fast as it
+        // accepts the connection.
+        let (stream, encoding) = with_timeout(opts.connect_timeout, async {
+            let mut stream = TcpStream::connect(socket_addr).await?;
+
+            let hello = Hello::new(encodings);
+            write_frame(&mut stream, 0, &hello.to_bytes()?).await?;
+
+            handshake::negotiated(read_frame(&mut stream, max_size).await?, &hello).map(|encoding| (stream, encoding))
+        }).await?;
+
+        let (mut reader, writer) = stream.into_split();
+        let pending = Arc::new(Pending::new());
+
+        let p = Arc::clone(&pending);
+        let reader = tokio::spawn(async move {
+            while let Ok(Some((len, id))) = read_header(&mut reader).await {
+                let reply = if len > max_size {
+                    tracing::warn!(id, len, max = max_size, "reply is too large");
+
+                    match skip_payload(&mut reader, len).await {
+                        Ok(()) => Err(Error::MessageTooLarge),
+                        Err(_) => break,
+                    }
+                } else {
+                    match read_payload(&mut reader, len).await {
+                        Ok(buf) => Ok(buf),
+                        Err(_) => break,
+                    }
+                };
+
+                p.route(id, reply);
+            }
+
+            p.close();
+        });
+
+        Ok(AsyncConnection {
+            encoding,
+            writer: AsyncMutex::new(writer),
+            write_timeout: opts.write_timeout,
+            pending,
+            last_used: Mutex::new(Instant::now()),
+            reader,
+        })
+    }
+
+    /// Send the request and wait for the reply, and its metadata, until the
+    /// deadline.
+    async fn call(&self, bin: &[u8], deadline: Option<Instant>) -> Result<(Metadata, Vec<u8>), Error> {
+        *self.last_used.lock().unwrap() = Instant::now();
+
+        let (tx, rx) = oneshot::channel();
+        let id = self.pending.insert(tx)?;
+
+        let res = with_timeout(self.write_timeout, async { write_frame(&mut *self.writer.lock().await, id, bin).await }).await;
+        if let Err(e) = res {
+            self.close();
+            return Err(Error::from(e));
+        }
+
+        let buf = match deadline {
+            Some(deadline) => match tokio::time::timeout_at(deadline.into(), rx).await {
+                Ok(res) => res.map_err(|_| closed_error())??,
+                Err(_) => {
+                    // A reply coming later is dropped by the reading task.
+                    self.pending.remove(id);
+
+                    return Err(Error::from(deadline_error()));
+                }
+            },
+            None => rx.await.map_err(|_| closed_error())??,
+        };
+
+        Reply::from_bytes(buf)?.into_body()
+    }
+
+    /// Return true when the connection can't be used anymore.
+    fn is_closed(&self) -> bool {
+        self.pending.is_closed()
+    }
+
+    /// Return true when the connection has been unused for longer than the
+    /// timeout and no request is waiting for a reply.
+    fn is_expired(&self, idle_timeout: Duration) -> bool {
+        self.pending.len() == 0 && self.last_used.lock().unwrap().elapsed() >= idle_timeout
+    }
+
+    /// Stop reading the replies and wake up the callers still waiting.
+    fn close(&self) {
+        self.reader.abort();
+        self.pending.close();
+    }
+}
+
+impl Drop for AsyncConnection {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// AsyncClientTransport implementation over TCP using the tokio runtime.
+/// A single connection is shared by the concurrent requests and it is
+/// opened again if it gets closed. Messages are serialized with the codec
+/// which is JSON by default.
+pub struct AsyncTcpClientTransport<C = JsonCodec> {
+    addr: Address,
+    conn: Mutex<Option<Arc<AsyncConnection>>>,
+    opts: ConnectOptions,
+    codec: PhantomData<C>,
+}
+
+impl AsyncTcpClientTransport {
+    /// Create a client transport that will try to connect
+    /// to the server at the given address.
+    pub fn new(addr: Address) -> AsyncTcpClientTransport {
+        AsyncTcpClientTransport::with_codec(addr)
+    }
+}
+
+impl<C: Codec> AsyncTcpClientTransport<C> {
+    /// Create a client transport that will use the codec given as type
+    /// parameter to serialize the messages.
+    pub fn with_codec(addr: Address) -> AsyncTcpClientTransport<C> {
+        // The defaults are the ones of the blocking client.
+        let config = PoolConfig::default();
+
+        AsyncTcpClientTransport {
+            addr,
+            conn: Mutex::new(None),
+            opts: ConnectOptions {
+                max_message_size: config.max_message_size,
+                connect_timeout: config.connect_timeout,
+                write_timeout: config.write_timeout,
+                call_timeout: config.call_timeout,
+                idle_timeout: config.idle_timeout,
+            },
+            codec: PhantomData,
+        }
+    }
+
+    /// Set the size in bytes of the largest reply accepted. Larger ones
+    /// fail the request without being read in memory.
+    pub fn with_max_message_size(mut self, max: usize) -> Self {
+        self.opts.max_message_size = max;
+        self
+    }
+
+    /// Set the duration after which opening a connection and negotiating
+    /// the protocol with the server fails, which is 5 seconds by default, or
+    /// None to wait as long as needed.
+    pub fn with_connect_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.opts.connect_timeout = timeout;
+        self
+    }
+
+    /// Set the duration after which writing a request fails, which is 5
+    /// seconds by default, or None to wait as long as needed.
+    pub fn with_write_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.opts.write_timeout = timeout;
+        self
+    }
+
+    /// Set the duration after which a request without a deadline of its own
+    /// fails with TimedOut, which is 30 seconds by default, or None to wait
+    /// for the reply as long as needed.
+    pub fn with_call_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.opts.call_timeout = timeout;
+        self
+    }
+
+    /// Set the duration after which an unused connection is opened again,
+    /// which is 4 seconds by default. It should be lower than the read
+    /// timeout of the server so that the client never sends a request on a
+    /// connection that the server is about to close.
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.opts.idle_timeout = timeout;
+        self
+    }
+
+    /// Get the current connection or open a new one if none is usable.
+    async fn checkout(&self) -> Result<Arc<AsyncConnection>, Error> {
+        if let Some(conn) = self.conn.lock().unwrap().as_ref() {
+            if !conn.is_closed() && !conn.is_expired(self.opts.idle_timeout) {
+                return Ok(Arc::clone(conn));
+            }
+        }
+
+        let conn = Arc::new(AsyncConnection::open(&self.addr, encodings::<C>(), &self.opts).await?);
+        *self.conn.lock().unwrap() = Some(Arc::clone(&conn));
+
+        Ok(conn)
+    }
+}
+
+impl<Req, Rep, C> AsyncClientTransport<Req, Rep> for AsyncTcpClientTransport<C>
+where
+    for<'de> Rep: Deserialize<'de> + Send,
+    Req: Serialize + Send,
+    C: Codec,
+{
+    type Error = Error;
+
+    /// Send the message to the server and resolve to the reply.
+    async fn send(&self, msg: Req) -> Result<Rep, Error> {
+        self.send_with_deadline(msg, None).await
+    }
+
+    /// Send the message to the server and resolve to the reply, or to an
+    /// error when the deadline elapses first.
+    async fn send_with_deadline(&self, msg: Req, deadline: Option<Instant>) -> Result<Rep, Error> {
+        let opts = CallOptions {
+            deadline,
+            ..CallOptions::default()
+        };
+
+        self.send_with_options(msg, &opts).await.map(|(rep, _)| rep)
+    }
+
+    /// Send the message with the metadata of the options and resolve to the
+    /// reply with the metadata of the server.
+    async fn send_with_options(&self, msg: Req, opts: &CallOptions) -> Result<(Rep, Metadata), Error> {
+        // The call timeout stands for the deadline of a request that has none.
+        let deadline = opts.deadline.or_else(|| self.opts.call_timeout.map(|timeout| Instant::now() + timeout));
+        let conn = self.checkout().await?;
+
+        let bin = encode_with::<C, _>(&conn.encoding, &msg)?;
+        let req = Request::new(bin, deadline, opts.metadata.clone()).to_bytes();
+
+        let (metadata, buf) = conn.call(&req[..], deadline).await?;
+
+        Ok((decode_with::<C, _>(&conn.encoding, &buf[..])?, metadata))
+    }
+}
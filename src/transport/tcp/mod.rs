@@ -0,0 +1,127 @@
+extern crate mio;
+extern crate serde;
+
+#[cfg(feature = "async")]
+mod asynchronous;
+
+#[cfg(feature = "async")]
+pub use self::asynchronous::{AsyncTcpClientTransport, AsyncTcpServerTransport};
+pub use super::stream::{Error, IoError, Overflow, PoolConfig, WorkerConfig};
+
+use super::super::{
+    executor::{Executor, ThreadPool},
+    group::Address,
+};
+use super::codec::{Codec, JsonCodec};
+use super::stream::{Accept, ServeOptions, StreamClientTransport, StreamServerTransport};
+use mio::net::TcpListener;
+use mio::{Poll, PollOpt, Ready, Token};
+use std::io;
+use std::net::TcpStream;
+
+/// ServerTransport implementation over TCP and using the codec to
+/// serialize the messages, which is JSON by default. The requests are
+/// processed by the executor, which is a ThreadPool by default.
+pub type TcpServerTransport<C = JsonCodec, E = ThreadPool> = StreamServerTransport<TcpStream, C, E>;
+
+/// ClientTransport implementation over TCP. Connections are kept
+/// in a pool and shared by the concurrent requests. Messages are
+/// serialized with the codec which is JSON by default.
+pub type TcpClientTransport<C = JsonCodec> = StreamClientTransport<TcpStream, C>;
+
+impl TcpServerTransport {
+    /// Create a transport object. The socket will be bind to
+    /// the given address.
+    pub fn new(addr: Address) -> io::Result<TcpServerTransport> {
+        TcpServerTransport::with_worker_config(addr, WorkerConfig::default())
+    }
+
+    /// Create a transport object that will process the requests with a
+    /// custom configuration of the pool of workers.
+    pub fn with_worker_config(addr: Address, config: WorkerConfig) -> io::Result<TcpServerTransport> {
+        TcpServerTransport::with_codec(addr, config)
+    }
+}
+
+impl<E: Executor> TcpServerTransport<JsonCodec, E> {
+    /// Create a transport object that will process the requests with the
+    /// executor. The overflow tells what to do with a request when the
+    /// executor is full.
+    pub fn with_executor(addr: Address, executor: E, overflow: Overflow) -> io::Result<TcpServerTransport<JsonCodec, E>> {
+        TcpServerTransport::with_codec_and_executor(addr, executor, overflow)
+    }
+}
+
+impl<C: Codec> TcpServerTransport<C> {
+    /// Create a transport object that will use the codec given as type
+    /// parameter to serialize the messages.
+    pub fn with_codec(addr: Address, config: WorkerConfig) -> io::Result<TcpServerTransport<C>> {
+        TcpServerTransport::with_codec_and_executor(addr, config.pool()?, config.overflow)
+    }
+}
+
+impl<C: Codec, E: Executor> TcpServerTransport<C, E> {
+    /// Create a transport object that will use the codec given as type
+    /// parameter to serialize the messages, and the executor to process
+    /// the requests.
+    pub fn with_codec_and_executor(addr: Address, executor: E, overflow: Overflow) -> io::Result<TcpServerTransport<C, E>> {
+        StreamServerTransport::with_acceptor(addr, (), executor, overflow)
+    }
+}
+
+impl Accept for TcpStream {
+    type Listener = TcpListener;
+    type Acceptor = ();
+    type Incoming = TcpStream;
+
+    /// Try to bind to the socket address. The port is chosen by the OS when
+    /// the address asks for port 0.
+    fn bind(addr: &Address, poll: &Poll) -> Result<(TcpListener, Address), Error> {
+        let socket_addr = match addr.get_socket_addr() {
+            Some(addr) => addr,
+            None => return Err(Error::NoSocketAddress),
+        };
+        let socket = TcpListener::bind(&socket_addr)?;
+        let addr = Address::Socket(socket.local_addr()?);
+
+        poll.register(&socket, Token(0), Ready::readable(), PollOpt::edge())?;
+
+        Ok((socket, addr))
+    }
+
+    fn accept(socket: &TcpListener) -> io::Result<(TcpStream, Address)> {
+        let (stream, sock_addr) = socket.accept_std()?;
+
+        Ok((stream, Address::Socket(sock_addr)))
+    }
+
+    fn open(stream: TcpStream, _: &(), _: &ServeOptions) -> Result<TcpStream, Error> {
+        Ok(stream)
+    }
+
+    fn close(socket: TcpListener, _: &Address, poll: &Poll) -> io::Result<()> {
+        poll.deregister(&socket)
+    }
+}
+
+impl TcpClientTransport {
+    /// Create a client transport that will try to connect
+    /// to the server at the given address.
+    pub fn new(addr: Address) -> TcpClientTransport {
+        TcpClientTransport::with_pool_config(addr, PoolConfig::default())
+    }
+
+    /// Create a client transport that will try to connect to the
+    /// server at the given address with a custom pool configuration.
+    pub fn with_pool_config(addr: Address, config: PoolConfig) -> TcpClientTransport {
+        TcpClientTransport::with_codec(addr, config)
+    }
+}
+
+impl<C: Codec> TcpClientTransport<C> {
+    /// Create a client transport that will use the codec given as type
+    /// parameter to serialize the messages.
+    pub fn with_codec(addr: Address, config: PoolConfig) -> TcpClientTransport<C> {
+        StreamClientTransport::with_connector(addr, config, ())
+    }
+}
@@ -0,0 +1,256 @@
+pub use super::stream::{Error, IoError, Overflow, PoolConfig, WorkerConfig};
+
+use super::super::{
+    executor::{Executor, ThreadPool},
+    group::Address,
+};
+use super::codec::{Codec, JsonCodec};
+use super::stream::{tls::TlsStream, Accept, ServeOptions, StreamClientTransport, StreamServerTransport};
+use mio::net::TcpListener;
+use mio::Poll;
+use rustls::server::AllowAnyAuthenticatedClient;
+use rustls::{Certificate, ClientConfig, PrivateKey, RootCertStore, ServerConfig, ServerName};
+use rustls_pemfile::Item;
+use std::convert::TryFrom;
+use std::io;
+use std::net::TcpStream;
+use std::sync::Arc;
+
+/// Certificate and private key used by a server to authenticate itself to
+/// the clients.
+#[derive(Clone)]
+pub struct TlsServerConfig {
+    inner: Arc<ServerConfig>,
+}
+
+impl TlsServerConfig {
+    /// Create a configuration from the PEM encoded certificate chain of the
+    /// server, leaf first, and its private key.
+    pub fn new(cert_pem: &[u8], key_pem: &[u8]) -> Result<TlsServerConfig, Error> {
+        let config = ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(read_certs(cert_pem)?, read_key(key_pem)?)
+            .map_err(invalid_config)?;
+
+        Ok(TlsServerConfig {
+            inner: Arc::new(config),
+        })
+    }
+
+    /// Create a configuration that also requires the clients to present a
+    /// certificate issued by one of the PEM encoded authorities. Clients
+    /// failing to do so are rejected during the handshake.
+    pub fn with_client_ca(cert_pem: &[u8], key_pem: &[u8], ca_pem: &[u8]) -> Result<TlsServerConfig, Error> {
+        let verifier = AllowAnyAuthenticatedClient::new(read_roots(ca_pem)?);
+
+        let config = ServerConfig::builder()
+            .with_safe_defaults()
+            .with_client_cert_verifier(verifier.boxed())
+            .with_single_cert(read_certs(cert_pem)?, read_key(key_pem)?)
+            .map_err(invalid_config)?;
+
+        Ok(TlsServerConfig {
+            inner: Arc::new(config),
+        })
+    }
+}
+
+impl From<Arc<ServerConfig>> for TlsServerConfig {
+    /// Use a configuration built with rustls for the settings that the
+    /// constructors don't cover, for instance to make client certificates
+    /// optional or to restrict the protocol versions. Mutual TLS only needs
+    /// TlsServerConfig::with_client_ca.
+    fn from(inner: Arc<ServerConfig>) -> Self {
+        TlsServerConfig { inner }
+    }
+}
+
+/// Certificate authorities trusted by a client to authenticate the server
+/// and the optional identity of the client.
+#[derive(Clone)]
+pub struct TlsClientConfig {
+    pub(crate) inner: Arc<ClientConfig>,
+    pub(crate) server_name: Option<ServerName>,
+}
+
+impl TlsClientConfig {
+    /// Create a configuration that trusts the PEM encoded certificates of
+    /// the authorities.
+    pub fn new(ca_pem: &[u8]) -> Result<TlsClientConfig, Error> {
+        let config = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(read_roots(ca_pem)?)
+            .with_no_client_auth();
+
+        Ok(TlsClientConfig::from(Arc::new(config)))
+    }
+
+    /// Create a configuration that trusts the PEM encoded certificates of
+    /// the authorities, and that presents the certificate chain of the
+    /// client with its private key when the server asks for it.
+    pub fn with_identity(ca_pem: &[u8], cert_pem: &[u8], key_pem: &[u8]) -> Result<TlsClientConfig, Error> {
+        let config = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(read_roots(ca_pem)?)
+            .with_client_auth_cert(read_certs(cert_pem)?, read_key(key_pem)?)
+            .map_err(invalid_config)?;
+
+        Ok(TlsClientConfig::from(Arc::new(config)))
+    }
+
+    /// Set the name expected in the certificate of the server. The IP
+    /// address of the server is expected otherwise.
+    pub fn with_server_name(mut self, name: &str) -> Result<TlsClientConfig, Error> {
+        self.server_name = Some(ServerName::try_from(name).map_err(invalid_config)?);
+
+        Ok(self)
+    }
+}
+
+impl From<Arc<ClientConfig>> for TlsClientConfig {
+    /// Use a configuration built with rustls.
+    fn from(inner: Arc<ClientConfig>) -> Self {
+        TlsClientConfig {
+            inner,
+            server_name: None,
+        }
+    }
+}
+
+fn invalid_config<E: std::fmt::Display>(err: E) -> Error {
+    Error::InvalidTlsConfig(err.to_string())
+}
+
+fn read_certs(mut pem: &[u8]) -> Result<Vec<Certificate>, Error> {
+    let certs = rustls_pemfile::certs(&mut pem).map_err(invalid_config)?;
+    if certs.is_empty() {
+        return Err(invalid_config("no certificate found"));
+    }
+
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+fn read_roots(pem: &[u8]) -> Result<RootCertStore, Error> {
+    let mut roots = RootCertStore::empty();
+    for cert in read_certs(pem)? {
+        roots.add(&cert).map_err(invalid_config)?;
+    }
+
+    Ok(roots)
+}
+
+fn read_key(mut pem: &[u8]) -> Result<PrivateKey, Error> {
+    while let Some(item) = rustls_pemfile::read_one(&mut pem).map_err(invalid_config)? {
+        match item {
+            Item::RSAKey(key) | Item::PKCS8Key(key) | Item::ECKey(key) => return Ok(PrivateKey(key)),
+            _ => (),
+        }
+    }
+
+    Err(invalid_config("no private key found"))
+}
+
+/// ServerTransport implementation over TCP where the connections are
+/// encrypted with TLS, and using the codec to serialize the messages,
+/// which is JSON by default. The requests are processed by the executor,
+/// which is a ThreadPool by default.
+pub type TlsServerTransport<C = JsonCodec, E = ThreadPool> = StreamServerTransport<TlsStream, C, E>;
+
+impl TlsServerTransport {
+    /// Create a transport object. The socket will be bind to
+    /// the given address.
+    pub fn new(addr: Address, config: TlsServerConfig) -> io::Result<TlsServerTransport> {
+        TlsServerTransport::with_worker_config(addr, config, WorkerConfig::default())
+    }
+
+    /// Create a transport object that will process the requests with a
+    /// custom configuration of the pool of workers.
+    pub fn with_worker_config(addr: Address, config: TlsServerConfig, worker_config: WorkerConfig) -> io::Result<TlsServerTransport> {
+        TlsServerTransport::with_codec(addr, config, worker_config)
+    }
+}
+
+impl<E: Executor> TlsServerTransport<JsonCodec, E> {
+    /// Create a transport object that will process the requests with the
+    /// executor. The overflow tells what to do with a request when the
+    /// executor is full.
+    pub fn with_executor(addr: Address, config: TlsServerConfig, executor: E, overflow: Overflow) -> io::Result<TlsServerTransport<JsonCodec, E>> {
+        TlsServerTransport::with_codec_and_executor(addr, config, executor, overflow)
+    }
+}
+
+impl<C: Codec> TlsServerTransport<C> {
+    /// Create a transport object that will use the codec given as type
+    /// parameter to serialize the messages.
+    pub fn with_codec(addr: Address, config: TlsServerConfig, worker_config: WorkerConfig) -> io::Result<TlsServerTransport<C>> {
+        TlsServerTransport::with_codec_and_executor(addr, config, worker_config.pool()?, worker_config.overflow)
+    }
+}
+
+impl<C: Codec, E: Executor> TlsServerTransport<C, E> {
+    /// Create a transport object that will use the codec given as type
+    /// parameter to serialize the messages, and the executor to process
+    /// the requests.
+    pub fn with_codec_and_executor(
+        addr: Address,
+        config: TlsServerConfig,
+        executor: E,
+        overflow: Overflow,
+    ) -> io::Result<TlsServerTransport<C, E>> {
+        StreamServerTransport::with_acceptor(addr, config, executor, overflow)
+    }
+}
+
+impl Accept for TlsStream {
+    type Listener = TcpListener;
+    type Acceptor = TlsServerConfig;
+    type Incoming = TcpStream;
+
+    /// Try to bind to the socket address. The port is chosen by the OS when
+    /// the address asks for port 0.
+    fn bind(addr: &Address, poll: &Poll) -> Result<(TcpListener, Address), Error> {
+        <TcpStream as Accept>::bind(addr, poll)
+    }
+
+    fn accept(socket: &TcpListener) -> io::Result<(TcpStream, Address)> {
+        <TcpStream as Accept>::accept(socket)
+    }
+
+    /// Run the TLS handshake with the client.
+    fn open(stream: TcpStream, config: &TlsServerConfig, opts: &ServeOptions) -> Result<TlsStream, Error> {
+        TlsStream::accept(stream, Arc::clone(&config.inner), opts)
+    }
+
+    fn close(socket: TcpListener, addr: &Address, poll: &Poll) -> io::Result<()> {
+        <TcpStream as Accept>::close(socket, addr, poll)
+    }
+}
+
+/// ClientTransport implementation over TCP where the connections are
+/// encrypted with TLS. Connections are kept in a pool and shared by the
+/// concurrent requests. Messages are serialized with the codec which is
+/// JSON by default.
+pub type TlsClientTransport<C = JsonCodec> = StreamClientTransport<TlsStream, C>;
+
+impl TlsClientTransport {
+    /// Create a client transport that will try to connect
+    /// to the server at the given address.
+    pub fn new(addr: Address, config: TlsClientConfig) -> TlsClientTransport {
+        TlsClientTransport::with_pool_config(addr, config, PoolConfig::default())
+    }
+
+    /// Create a client transport that will try to connect to the
+    /// server at the given address with a custom pool configuration.
+    pub fn with_pool_config(addr: Address, config: TlsClientConfig, pool_config: PoolConfig) -> TlsClientTransport {
+        TlsClientTransport::with_codec(addr, config, pool_config)
+    }
+}
+
+impl<C: Codec> TlsClientTransport<C> {
+    /// Create a client transport that will use the codec given as type
+    /// parameter to serialize the messages.
+    pub fn with_codec(addr: Address, config: TlsClientConfig, pool_config: PoolConfig) -> TlsClientTransport<C> {
+        StreamClientTransport::with_connector(addr, pool_config, config)
+    }
+}
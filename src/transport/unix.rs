@@ -0,0 +1,141 @@
+pub use super::stream::{Error, IoError, Overflow, PoolConfig, WorkerConfig};
+
+use super::super::{
+    executor::{Executor, ThreadPool},
+    group::Address,
+};
+use super::codec::{Codec, JsonCodec};
+use super::stream::{Accept, ServeOptions, StreamClientTransport, StreamServerTransport};
+use mio::unix::EventedFd;
+use mio::{Poll, PollOpt, Ready, Token};
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+
+/// ServerTransport implementation over a Unix domain socket and using
+/// the codec to serialize the messages, which is JSON by default. The
+/// requests are processed by the executor, which is a ThreadPool by
+/// default.
+pub type UnixServerTransport<C = JsonCodec, E = ThreadPool> = StreamServerTransport<UnixStream, C, E>;
+
+/// ClientTransport implementation over a Unix domain socket. Connections
+/// are kept in a pool and shared by the concurrent requests. Messages are
+/// serialized with the codec which is JSON by default.
+pub type UnixClientTransport<C = JsonCodec> = StreamClientTransport<UnixStream, C>;
+
+impl UnixServerTransport {
+    /// Create a transport object. The socket will be bind to
+    /// the path of the given address.
+    pub fn new(addr: Address) -> io::Result<UnixServerTransport> {
+        UnixServerTransport::with_worker_config(addr, WorkerConfig::default())
+    }
+
+    /// Create a transport object that will process the requests with a
+    /// custom configuration of the pool of workers.
+    pub fn with_worker_config(addr: Address, config: WorkerConfig) -> io::Result<UnixServerTransport> {
+        UnixServerTransport::with_codec(addr, config)
+    }
+}
+
+impl<E: Executor> UnixServerTransport<JsonCodec, E> {
+    /// Create a transport object that will process the requests with the
+    /// executor. The overflow tells what to do with a request when the
+    /// executor is full.
+    pub fn with_executor(addr: Address, executor: E, overflow: Overflow) -> io::Result<UnixServerTransport<JsonCodec, E>> {
+        UnixServerTransport::with_codec_and_executor(addr, executor, overflow)
+    }
+}
+
+impl<C: Codec> UnixServerTransport<C> {
+    /// Create a transport object that will use the codec given as type
+    /// parameter to serialize the messages.
+    pub fn with_codec(addr: Address, config: WorkerConfig) -> io::Result<UnixServerTransport<C>> {
+        UnixServerTransport::with_codec_and_executor(addr, config.pool()?, config.overflow)
+    }
+}
+
+impl<C: Codec, E: Executor> UnixServerTransport<C, E> {
+    /// Create a transport object that will use the codec given as type
+    /// parameter to serialize the messages, and the executor to process
+    /// the requests.
+    pub fn with_codec_and_executor(addr: Address, executor: E, overflow: Overflow) -> io::Result<UnixServerTransport<C, E>> {
+        StreamServerTransport::with_acceptor(addr, (), executor, overflow)
+    }
+}
+
+impl Accept for UnixStream {
+    type Listener = UnixListener;
+    type Acceptor = ();
+    type Incoming = UnixStream;
+
+    /// Try to bind to the socket path.
+    fn bind(addr: &Address, poll: &Poll) -> Result<(UnixListener, Address), Error> {
+        let path = match addr {
+            Address::Unix(path) => path,
+            _ => return Err(Error::NoUnixPath),
+        };
+
+        let socket = UnixListener::bind(path)?;
+        socket.set_nonblocking(true)?;
+
+        poll.register(
+            &EventedFd(&socket.as_raw_fd()),
+            Token(0),
+            Ready::readable(),
+            PollOpt::edge(),
+        )?;
+
+        Ok((socket, addr.clone()))
+    }
+
+    fn accept(socket: &UnixListener) -> io::Result<(UnixStream, Address)> {
+        let (stream, peer) = socket.accept()?;
+
+        // The listener is non-blocking but the connections are not.
+        stream.set_nonblocking(false)?;
+
+        // Clients are usually bound to an unnamed socket.
+        Ok((stream, Address::Unix(peer.as_pathname().map(PathBuf::from).unwrap_or_default())))
+    }
+
+    fn open(stream: UnixStream, _: &(), _: &ServeOptions) -> Result<UnixStream, Error> {
+        Ok(stream)
+    }
+
+    fn close(socket: UnixListener, addr: &Address, poll: &Poll) -> io::Result<()> {
+        let res = poll.deregister(&EventedFd(&socket.as_raw_fd()));
+
+        // The socket file is left behind by the listener. Failing to remove
+        // it only leaves the path taken until someone cleans it up.
+        if let Address::Unix(path) = addr {
+            if let Err(e) = std::fs::remove_file(path) {
+                tracing::warn!(path = %path.display(), error = %e, "socket file can't be removed");
+            }
+        }
+
+        res
+    }
+}
+
+impl UnixClientTransport {
+    /// Create a client transport that will try to connect
+    /// to the server at the given address.
+    pub fn new(addr: Address) -> UnixClientTransport {
+        UnixClientTransport::with_pool_config(addr, PoolConfig::default())
+    }
+
+    /// Create a client transport that will try to connect to the
+    /// server at the given address with a custom pool configuration.
+    pub fn with_pool_config(addr: Address, config: PoolConfig) -> UnixClientTransport {
+        UnixClientTransport::with_codec(addr, config)
+    }
+}
+
+impl<C: Codec> UnixClientTransport<C> {
+    /// Create a client transport that will use the codec given as type
+    /// parameter to serialize the messages.
+    pub fn with_codec(addr: Address, config: PoolConfig) -> UnixClientTransport<C> {
+        StreamClientTransport::with_connector(addr, config, ())
+    }
+}
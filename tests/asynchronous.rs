@@ -0,0 +1,151 @@
+#![cfg(feature = "async")]
+
+mod common;
+
+use common::TestError;
+use rpc::transport::CallError;
+use rpc::group::Address;
+use rpc::transport::tcp::{
+    AsyncTcpClientTransport,
+    AsyncTcpServerTransport,
+    TcpClientTransport,
+};
+
+#[rpc_macro::service]
+trait Greeter {
+    async fn greet(&self, ctx: Context, name: String) -> Result<String, TestError>;
+    fn ping(&self, ctx: Context, v: u64) -> Result<u64, TestError>;
+}
+
+struct GreeterService;
+
+impl Greeter for GreeterService {
+    async fn greet(&self, ctx: Context, name: String) -> Result<String, TestError> {
+        tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+
+        let greeting = ctx.get_metadata().get("greeting").map_or("Hello", |g| g.as_str());
+        ctx.set_reply_metadata("greeted", name.clone());
+
+        Ok(format!("{} {}!", greeting, name))
+    }
+
+    fn ping(&self, _: Context, v: u64) -> Result<u64, TestError> {
+        if v == 0 {
+            panic!("ping of zero");
+        }
+
+        Ok(v)
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn async_service() {
+    let handle = rpc::serve(
+        GreeterService.get_processor(),
+        AsyncTcpServerTransport::new(Address::from_str("127.0.0.1:0")),
+    ).await.unwrap();
+    let addr = handle.get_addr().clone();
+
+    let c = AsyncGreeterClient::new(AsyncTcpClientTransport::new(addr.clone()));
+    assert_eq!(c.greet(String::from("Alice")).await.unwrap(), "Hello Alice!");
+    assert_eq!(c.ping(42).await.unwrap(), 42);
+
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    assert_eq!(c.ping_with_deadline(7, Some(deadline)).await.unwrap(), 7);
+
+    let opts = rpc::CallOptions {
+        metadata: rpc::Metadata::from([("greeting".to_string(), "Hi".to_string())]),
+        ..rpc::CallOptions::default()
+    };
+    let (greeting, metadata) = c.greet_with_options(String::from("Carol"), &opts).await.unwrap();
+    assert_eq!(greeting, "Hi Carol!");
+    assert_eq!(metadata.get("greeted").map(String::as_str), Some("Carol"));
+
+    match c.ping(0).await {
+        Err(TestError::Call(CallError::Internal(e))) => assert_eq!(e, "ping of zero"),
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    // The wire format is shared with the blocking transport.
+    let r = {
+        let addr = addr.clone();
+        tokio::task::spawn_blocking(move || {
+            let c = GreeterClient::new(TcpClientTransport::new(addr));
+            c.greet(String::from("Bob"))
+        })
+    };
+    assert_eq!(r.await.unwrap().unwrap(), "Hello Bob!");
+
+    // New connections are refused once the server is shut down.
+    handle.shutdown();
+
+    let mut refused = false;
+    for _ in 0..20 {
+        let c = AsyncGreeterClient::new(AsyncTcpClientTransport::new(addr.clone()));
+        if c.ping(1).await.is_err() {
+            refused = true;
+            break;
+        }
+
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    assert!(refused);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn message_too_large() {
+    let handle = rpc::serve(
+        GreeterService.get_processor(),
+        AsyncTcpServerTransport::new(Address::from_str("127.0.0.1:0")).with_max_message_size(1024),
+    ).await.unwrap();
+    let addr = handle.get_addr().clone();
+
+    let c = AsyncGreeterClient::new(AsyncTcpClientTransport::new(addr.clone()));
+
+    match c.greet("a".repeat(4096)).await {
+        Err(TestError::Call(CallError::MessageTooLarge)) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    // The connection can still be used by the next requests.
+    assert_eq!(c.greet(String::from("Alice")).await.unwrap(), "Hello Alice!");
+
+    let c = AsyncGreeterClient::new(AsyncTcpClientTransport::new(addr).with_max_message_size(64));
+
+    match c.greet("a".repeat(128)).await {
+        Err(TestError::Call(CallError::MessageTooLarge)) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    assert_eq!(c.greet(String::from("Bob")).await.unwrap(), "Hello Bob!");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn timeouts() {
+    use std::time::{Duration, Instant};
+    use tokio::io::AsyncReadExt;
+
+    let handle = rpc::serve(
+        GreeterService.get_processor(),
+        AsyncTcpServerTransport::new(Address::from_str("127.0.0.1:0")).with_read_timeout(Some(Duration::from_millis(100))),
+    ).await.unwrap();
+    let socket_addr = handle.get_addr().get_socket_addr().unwrap();
+
+    // A connection that never sends anything is closed by the server.
+    let mut stream = tokio::net::TcpStream::connect(socket_addr).await.unwrap();
+    let mut buf = [0u8; 1];
+    let n = tokio::time::timeout(Duration::from_secs(2), stream.read(&mut buf)).await.unwrap();
+    assert_eq!(n.unwrap(), 0);
+
+    // The client does not wait forever for a server that never answers.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = Address::Socket(listener.local_addr().unwrap());
+    let hung = tokio::spawn(async move { listener.accept().await });
+
+    let c = AsyncGreeterClient::new(AsyncTcpClientTransport::new(addr).with_connect_timeout(Some(Duration::from_millis(100))));
+    let start = Instant::now();
+    assert!(c.ping(1).await.is_err());
+    assert!(start.elapsed() < Duration::from_secs(2));
+
+    hung.abort();
+}
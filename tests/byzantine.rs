@@ -1,5 +1,10 @@
+mod common;
+
+use common::TestError;
+use rpc::transport::CallError;
 use rpc::Server;
 use rpc::group::Address;
+use rpc::transport::local::{LocalClientTransport, LocalServerTransport};
 use rpc::transport::tcp::{
     TcpClientTransport,
     TcpServerTransport,
@@ -7,42 +12,79 @@ use rpc::transport::tcp::{
 
 #[test]
 fn error() -> Result<(), ()> {
-    #[derive(Serialize, Deserialize, Debug)]
-    pub enum ByzantineError {
-        Error(String),
-    }
-
-    impl<E: std::error::Error + Sized> std::convert::From<E> for ByzantineError {
-        fn from(err: E) -> Self {
-            ByzantineError::Error(err.description().to_string())
-        }
-    }
-
     #[rpc_macro::service]
     trait Byzantine {
-        fn byzantine(&self, ctx: Context, arg: u64) -> Result<u64, ByzantineError>;
+        fn byzantine(&self, ctx: Context, arg: u64) -> Result<u64, TestError>;
     }
 
     struct ByzantineService;
 
     impl Byzantine for ByzantineService {
-        fn byzantine(&self, _: Context, _: u64) -> Result<u64, ByzantineError> {
+        fn byzantine(&self, _: Context, _: u64) -> Result<u64, TestError> {
             panic!("example panic in test");
         }
     }
 
-    let addr = Address::from_str("127.0.0.1:2002");
+    let addr = Address::from_str("127.0.0.1:0");
 
-    let mut srv = Server::new();
+    let srv = Server::new();
     let service = ByzantineService;
 
-    srv.run(
+    let handle = srv.run(
         service.get_processor(),
-        TcpServerTransport::new(addr.clone()).unwrap(),
-    );
+        TcpServerTransport::new(addr).unwrap(),
+    ).unwrap();
+    let addr = handle.get_addr().clone();
 
     let c = ByzantineClient::new(TcpClientTransport::new(addr));
-    assert!(c.byzantine(0).is_err());
+
+    // The server answers with the message of the panic and the connection
+    // can still be used afterwards.
+    for _ in 0..2 {
+        match c.byzantine(0) {
+            Err(TestError::Call(CallError::Internal(e))) => assert_eq!(e, "example panic in test"),
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+
+    let addr = Address::Local(String::from("byzantine"));
+    let _handle = srv.run(
+        ByzantineService.get_processor(),
+        LocalServerTransport::new(addr.clone()),
+    ).unwrap();
+
+    let c = ByzantineClient::new(LocalClientTransport::new(addr));
+    match c.byzantine(0) {
+        Err(TestError::Call(CallError::Internal(e))) => assert_eq!(e, "example panic in test"),
+        res => panic!("unexpected result: {:?}", res),
+    }
 
     Ok(())
 }
+
+#[test]
+fn bad_request() {
+    // Client of a service named like the one of the server but whose
+    // requests carry another type.
+    #[rpc_macro::service]
+    trait Echo {
+        fn echo(&self, ctx: Context, arg: u64) -> Result<u64, TestError>;
+    }
+
+    let srv = Server::new();
+    let handle = srv.run(
+        common::Echo::get_processor(common::EchoService),
+        TcpServerTransport::new(Address::from_str("127.0.0.1:0")).unwrap(),
+    ).unwrap();
+    let addr = handle.get_addr().clone();
+
+    let c = EchoClient::new(TcpClientTransport::new(addr.clone()));
+    match c.echo(42) {
+        Err(TestError::Call(CallError::BadRequest(_))) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    // The server keeps serving the clients sending valid requests.
+    let c = common::EchoClient::new(TcpClientTransport::new(addr));
+    assert_eq!(c.echo(String::from("deadbeef")).unwrap(), "deadbeef");
+}
@@ -0,0 +1,180 @@
+mod common;
+
+use common::TestError;
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use rpc::Server;
+use rpc::group::Address;
+use rpc::transport::local::{LocalClientTransport, LocalServerTransport};
+use rpc::transport::tcp::{
+    TcpClientTransport,
+    TcpServerTransport,
+};
+
+#[rpc_macro::service]
+trait Watch {
+    fn wait(&self, ctx: Context, ms: u64) -> Result<bool, TestError>;
+    fn watch(&self, ctx: Context, ms: u64) -> Result<bool, TestError>;
+}
+
+/// Service running for the given duration unless the request is cancelled
+/// and reporting whether it has been.
+struct WatchService {
+    outcomes: Mutex<mpsc::Sender<bool>>,
+}
+
+impl WatchService {
+    fn run(&self, ms: u64, is_cancelled: impl Fn() -> bool) -> bool {
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_millis(ms) && !is_cancelled() {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let cancelled = is_cancelled();
+        self.outcomes.lock().unwrap().send(cancelled).unwrap();
+
+        cancelled
+    }
+}
+
+impl Watch for WatchService {
+    fn wait(&self, ctx: Context, ms: u64) -> Result<bool, TestError> {
+        Ok(self.run(ms, || ctx.is_cancelled()))
+    }
+
+    /// Only the client disconnecting or the server giving up on the request
+    /// cancels it.
+    fn watch(&self, ctx: Context, ms: u64) -> Result<bool, TestError> {
+        let token = ctx.get_cancellation_token().with_deadline(None);
+        Ok(self.run(ms, || token.is_cancelled()))
+    }
+}
+
+fn start() -> (rpc::ServerHandle, mpsc::Receiver<bool>) {
+    let (tx, rx) = mpsc::channel();
+    let service = WatchService { outcomes: Mutex::new(tx) };
+
+    let srv = Server::new();
+    let handle = srv.run(
+        service.get_processor(),
+        TcpServerTransport::new(Address::from_str("127.0.0.1:0")).unwrap(),
+    ).unwrap();
+
+    (handle, rx)
+}
+
+fn start_local(name: &str) -> (rpc::ServerHandle, mpsc::Receiver<bool>) {
+    let (tx, rx) = mpsc::channel();
+    let service = WatchService { outcomes: Mutex::new(tx) };
+
+    let srv = Server::new();
+    let handle = srv.run(
+        service.get_processor(),
+        LocalServerTransport::new(Address::Local(name.to_string())),
+    ).unwrap();
+
+    (handle, rx)
+}
+
+#[test]
+fn deadline_elapsed() {
+    let (handle, outcomes) = start();
+    let c = WatchClient::new(TcpClientTransport::new(handle.get_addr().clone()));
+
+    assert!(c.wait(0).is_ok());
+    assert!(!outcomes.recv().unwrap());
+
+    let deadline = Instant::now() + Duration::from_millis(100);
+    assert!(c.wait_with_deadline(5000, Some(deadline)).is_err());
+    assert!(outcomes.recv_timeout(Duration::from_secs(1)).unwrap());
+}
+
+#[test]
+fn client_disconnected() {
+    let (handle, outcomes) = start();
+    let c = WatchClient::new(TcpClientTransport::new(handle.get_addr().clone()));
+
+    let deadline = Instant::now() + Duration::from_millis(100);
+    assert!(c.watch_with_deadline(5000, Some(deadline)).is_err());
+
+    // The request runs until the connection is closed.
+    assert!(outcomes.recv_timeout(Duration::from_millis(200)).is_err());
+    drop(c);
+    assert!(outcomes.recv_timeout(Duration::from_secs(1)).unwrap());
+}
+
+#[test]
+fn local_client_gave_up() {
+    let (handle, outcomes) = start_local("cancel-gave-up");
+    let c = WatchClient::new(LocalClientTransport::new(handle.get_addr().clone()));
+
+    assert!(c.watch(0).is_ok());
+    assert!(!outcomes.recv().unwrap());
+
+    // The request is cancelled as soon as the client stops waiting for it.
+    let deadline = Instant::now() + Duration::from_millis(100);
+    assert!(c.watch_with_deadline(5000, Some(deadline)).is_err());
+    assert!(outcomes.recv_timeout(Duration::from_secs(1)).unwrap());
+}
+
+#[test]
+fn shutdown() {
+    let (handle, outcomes) = start();
+    let c = WatchClient::new(TcpClientTransport::new(handle.get_addr().clone()));
+    let req = thread::spawn(move || c.watch(300));
+
+    // The request is drained without being cancelled.
+    thread::sleep(Duration::from_millis(100));
+    assert_eq!(handle.shutdown(Duration::from_secs(5)).unwrap(), 0);
+    assert!(!req.join().unwrap().unwrap());
+    assert!(!outcomes.recv().unwrap());
+
+    // The request still running after the deadline is cancelled.
+    let (handle, outcomes) = start();
+    let c = WatchClient::new(TcpClientTransport::new(handle.get_addr().clone()));
+    let req = thread::spawn(move || c.watch(5000));
+
+    thread::sleep(Duration::from_millis(100));
+    assert_eq!(handle.shutdown(Duration::from_millis(100)).unwrap(), 1);
+    assert!(req.join().unwrap().is_err());
+    assert!(outcomes.recv_timeout(Duration::from_secs(1)).unwrap());
+}
+
+#[cfg(feature = "tls")]
+#[test]
+fn tls_shutdown() {
+    use rcgen::{BasicConstraints, Certificate, CertificateParams, IsCa, SanType};
+    use rpc::transport::tls::{TlsClientConfig, TlsClientTransport, TlsServerConfig, TlsServerTransport};
+
+    let mut params = CertificateParams::new(vec![]);
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    let ca = Certificate::from_params(params).unwrap();
+
+    let mut params = CertificateParams::new(vec![]);
+    params.subject_alt_names = vec![SanType::IpAddress("127.0.0.1".parse().unwrap())];
+    let server = Certificate::from_params(params).unwrap();
+    let config = TlsServerConfig::new(
+        server.serialize_pem_with_signer(&ca).unwrap().as_bytes(),
+        server.serialize_private_key_pem().as_bytes(),
+    ).unwrap();
+
+    let (tx, outcomes) = mpsc::channel();
+    let service = WatchService { outcomes: Mutex::new(tx) };
+
+    let srv = Server::new();
+    let handle = srv.run(
+        service.get_processor(),
+        TlsServerTransport::new(Address::from_str("127.0.0.1:0"), config).unwrap(),
+    ).unwrap();
+
+    let config = TlsClientConfig::new(ca.serialize_pem().unwrap().as_bytes()).unwrap();
+    let c = WatchClient::new(TlsClientTransport::new(handle.get_addr().clone(), config));
+    let req = thread::spawn(move || c.wait(500));
+
+    // Closing the connections does not cancel the requests being drained.
+    thread::sleep(Duration::from_millis(100));
+    assert_eq!(handle.shutdown(Duration::from_secs(5)).unwrap(), 0);
+    assert!(!req.join().unwrap().unwrap());
+    assert!(!outcomes.recv().unwrap());
+}
@@ -0,0 +1,71 @@
+#![cfg(any(feature = "bincode", feature = "msgpack"))]
+
+mod common;
+
+use common::TestError;
+use rpc::Server;
+use rpc::group::Address;
+use rpc::transport::Codec;
+use rpc::transport::tcp::{
+    PoolConfig,
+    TcpClientTransport,
+    TcpServerTransport,
+    WorkerConfig,
+};
+
+#[rpc_macro::service]
+trait Sum {
+    fn sum(&self, ctx: Context, values: Vec<f64>) -> Result<f64, TestError>;
+}
+
+struct SumService;
+
+impl Sum for SumService {
+    fn sum(&self, _: Context, values: Vec<f64>) -> Result<f64, TestError> {
+        Ok(values.iter().sum())
+    }
+}
+
+fn run_with_codec<C: Codec>() {
+    let addr = Address::from_str("127.0.0.1:0");
+
+    let srv = Server::new();
+    let handle = srv.run(
+        SumService.get_processor(),
+        TcpServerTransport::<C>::with_codec(addr, WorkerConfig::default()).unwrap(),
+    ).unwrap();
+    let addr = handle.get_addr().clone();
+
+    let c = SumClient::new(TcpClientTransport::<C>::with_codec(addr, PoolConfig::default()));
+    let values: Vec<f64> = (0..1000).map(|v| v as f64).collect();
+    assert_eq!(c.sum(values).unwrap(), 499500.0);
+}
+
+#[test]
+#[cfg(feature = "bincode")]
+fn bincode() {
+    run_with_codec::<rpc::transport::codec::BincodeCodec>();
+}
+
+#[test]
+#[cfg(feature = "msgpack")]
+fn msgpack() {
+    run_with_codec::<rpc::transport::codec::MsgPackCodec>();
+}
+
+#[test]
+#[cfg(feature = "bincode")]
+fn negotiation() {
+    let addr = Address::from_str("127.0.0.1:0");
+
+    let srv = Server::new();
+    let handle = srv.run(
+        SumService.get_processor(),
+        TcpServerTransport::<rpc::transport::codec::BincodeCodec>::with_codec(addr, WorkerConfig::default()).unwrap(),
+    ).unwrap();
+    let addr = handle.get_addr().clone();
+
+    // The client doesn't know bincode so both fall back to JSON.
+    let c = SumClient::new(TcpClientTransport::new(addr));
+    assert_eq!(c.sum(vec![1.0, 2.0]).unwrap(), 3.0);
+}
@@ -0,0 +1,44 @@
+//! Service and error shared by the integration tests. Every test does not
+//! use all of them.
+#![allow(dead_code)]
+
+use rpc::transport::CallError;
+use std::time::Duration;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum TestError {
+    Empty,
+    Error(String),
+    /// The call failed before the service could answer.
+    Call(CallError),
+}
+
+impl From<CallError> for TestError {
+    fn from(err: CallError) -> Self {
+        TestError::Call(err)
+    }
+}
+
+#[rpc_macro::service]
+trait Echo {
+    fn echo(&self, ctx: Context, arg: String) -> Result<String, TestError>;
+    fn sleep(&self, ctx: Context, ms: u64) -> Result<u64, TestError>;
+}
+
+pub struct EchoService;
+
+impl Echo for EchoService {
+    /// Return the argument, which must not be empty.
+    fn echo(&self, _: Context, arg: String) -> Result<String, TestError> {
+        if arg.is_empty() {
+            return Err(TestError::Empty);
+        }
+
+        Ok(arg)
+    }
+
+    fn sleep(&self, _: Context, ms: u64) -> Result<u64, TestError> {
+        std::thread::sleep(Duration::from_millis(ms));
+        Ok(ms)
+    }
+}
@@ -13,9 +13,10 @@ fn counter() {
     pub enum CounterError {
         IncrementError,
         Error(String),
-    };
+    }
 
     impl<E: std::error::Error + Sized> std::convert::From<E> for CounterError {
+        #[allow(deprecated)]
         fn from(err: E) -> Self {
             CounterError::Error(err.description().to_string())
         }
@@ -46,16 +47,17 @@ fn counter() {
         }
     }
 
-    let addr = Address::from_str("127.0.0.1:2001");
+    let addr = Address::from_str("127.0.0.1:0");
 
-    let mut srv = Server::new();
+    let srv = Server::new();
     let service = CounterService {
         value: AtomicU64::new(0),
     };
-    srv.run(
+    let handle = srv.run(
         service.get_processor(),
-        TcpServerTransport::new(addr.clone()).unwrap(),
-    );
+        TcpServerTransport::new(addr).unwrap(),
+    ).unwrap();
+    let addr = handle.get_addr().clone();
 
     let mut threads = Vec::new();
     let n = 5;
@@ -0,0 +1,100 @@
+mod common;
+
+use common::TestError;
+use rpc::transport::CallError;
+use std::thread;
+use std::time::{Duration, Instant};
+use rpc::Server;
+use rpc::group::Address;
+use rpc::transport::local::{LocalClientTransport, LocalServerTransport};
+use rpc::transport::tcp::{
+    PoolConfig,
+    TcpClientTransport,
+    TcpServerTransport,
+};
+
+#[rpc_macro::service]
+trait Wait {
+    fn wait(&self, ctx: Context, ms: u64) -> Result<bool, TestError>;
+}
+
+struct WaitService;
+
+impl Wait for WaitService {
+    /// Sleep for the given duration and tell if the request has a deadline.
+    fn wait(&self, ctx: Context, ms: u64) -> Result<bool, TestError> {
+        thread::sleep(Duration::from_millis(ms));
+        Ok(ctx.get_deadline().is_some())
+    }
+}
+
+fn assert_timed_out(res: Result<bool, TestError>) {
+    match res {
+        Err(TestError::Call(CallError::TimedOut)) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn call_deadline() {
+    let srv = Server::new();
+    let handle = srv.run(
+        WaitService.get_processor(),
+        TcpServerTransport::new(Address::from_str("127.0.0.1:0")).unwrap(),
+    ).unwrap();
+
+    // The call timeout of the client is the deadline of a request without
+    // one of its own, unless the client has none.
+    let config = PoolConfig {
+        call_timeout: None,
+        ..PoolConfig::default()
+    };
+    let c = WaitClient::new(TcpClientTransport::with_pool_config(handle.get_addr().clone(), config));
+    assert!(!c.wait(0).unwrap());
+
+    let c = WaitClient::new(TcpClientTransport::new(handle.get_addr().clone()));
+    assert!(c.wait(0).unwrap());
+
+    let deadline = Instant::now() + Duration::from_millis(100);
+    assert_timed_out(c.wait_with_deadline(500, Some(deadline)));
+    assert!(deadline.elapsed() < Duration::from_millis(300));
+
+    // The deadline is given to the server and the connection can still be
+    // used after a request timed out.
+    let deadline = Instant::now() + Duration::from_secs(5);
+    assert!(c.wait_with_deadline(0, Some(deadline)).unwrap());
+}
+
+#[test]
+fn call_timeout() {
+    let srv = Server::new();
+    let handle = srv.run(
+        WaitService.get_processor(),
+        TcpServerTransport::new(Address::from_str("127.0.0.1:0"))
+            .unwrap()
+            .with_read_timeout(Some(Duration::from_secs(1)))
+            .with_wait_timeout(Duration::from_millis(10)),
+    ).unwrap();
+
+    let config = PoolConfig {
+        call_timeout: Some(Duration::from_millis(100)),
+        ..PoolConfig::default()
+    };
+    let c = WaitClient::new(TcpClientTransport::with_pool_config(handle.get_addr().clone(), config));
+
+    assert_timed_out(c.wait(500));
+    assert!(c.wait(0).unwrap());
+}
+
+#[test]
+fn local_deadline() {
+    let addr = Address::Local(String::from("deadline"));
+
+    let srv = Server::new();
+    let _handle = srv.run(WaitService.get_processor(), LocalServerTransport::new(addr.clone())).unwrap();
+
+    let c = WaitClient::new(LocalClientTransport::new(addr));
+
+    assert_timed_out(c.wait_with_deadline(500, Some(Instant::now() + Duration::from_millis(100))));
+    assert!(c.wait_with_deadline(0, Some(Instant::now() + Duration::from_secs(5))).unwrap());
+}
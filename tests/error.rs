@@ -0,0 +1,113 @@
+mod common;
+
+use std::io;
+use std::net::TcpListener;
+use common::{ClientData, Echo, EchoClient, EchoService, ServerData, TestError};
+use serde::{Deserialize, Serialize};
+use rpc::Server;
+use rpc::transport::{CallError, ClientTransport};
+use rpc::group::Address;
+use rpc::transport::tcp::{
+    self,
+    TcpClientTransport,
+    TcpServerTransport,
+};
+
+/// Reply expected by a client that disagrees with the server on the type
+/// returned by the method.
+#[derive(Serialize, Deserialize, Debug)]
+enum WrongData {
+    Echo(u64),
+}
+
+#[test]
+fn connection_refused() {
+    // Nobody listens to the port once the listener is dropped.
+    let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+
+    let c = TcpClientTransport::new(Address::Socket(addr));
+    let res: Result<ServerData, tcp::Error> = c.send(ClientData::Echo(String::from("refused")));
+
+    match res {
+        Err(e) => assert_eq!(e.io_kind(), Some(io::ErrorKind::ConnectionRefused)),
+        Ok(rep) => panic!("unexpected reply: {:?}", rep),
+    }
+
+    // The generated client keeps the kind of the error.
+    let c = EchoClient::new(TcpClientTransport::new(Address::Socket(addr)));
+    match c.echo(String::from("refused")) {
+        Err(TestError::Call(CallError::Io(e))) => assert_eq!(e.kind(), io::ErrorKind::ConnectionRefused),
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
+/// Transport written outside of the crate, whose error knows nothing of the
+/// errors of the calls.
+struct Unreachable;
+
+#[derive(Debug)]
+struct UnreachableError;
+
+impl std::fmt::Display for UnreachableError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "server is unreachable")
+    }
+}
+
+impl std::error::Error for UnreachableError {}
+
+impl ClientTransport<ClientData, ServerData> for Unreachable {
+    type Error = UnreachableError;
+
+    fn send(&self, _: ClientData) -> Result<ServerData, UnreachableError> {
+        Err(UnreachableError)
+    }
+}
+
+#[test]
+fn custom_transport() {
+    let c = EchoClient::new(Unreachable);
+
+    match c.echo(String::from("unreachable")) {
+        Err(TestError::Call(CallError::Other(e))) => assert_eq!(e, "server is unreachable"),
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn malformed_reply() {
+    let srv = Server::new();
+    let handle = srv.run(
+        EchoService.get_processor(),
+        TcpServerTransport::new(Address::from_str("127.0.0.1:0")).unwrap(),
+    ).unwrap();
+
+    let c = TcpClientTransport::new(handle.get_addr().clone());
+    let res: Result<WrongData, tcp::Error> = c.send(ClientData::Echo(String::from("malformed")));
+
+    let err = match res {
+        Err(tcp::Error::SerdeError(err)) => err,
+        res => panic!("unexpected result: {:?}", res),
+    };
+    assert_eq!(err.get_line(), Some(1));
+    assert!(err.get_column().is_some());
+
+    // The details are kept when the error is sent to a peer.
+    let err = tcp::Error::SerdeError(err);
+    let buf = serde_json::to_vec(&err).unwrap();
+    match serde_json::from_slice(&buf).unwrap() {
+        tcp::Error::SerdeError(e) => assert_eq!(e.get_line(), Some(1)),
+        e => panic!("unexpected error: {:?}", e),
+    }
+}
+
+#[test]
+fn io_kind_serialization() {
+    let err = tcp::Error::from(io::Error::new(io::ErrorKind::ConnectionReset, "reset by peer"));
+
+    let buf = serde_json::to_vec(&err).unwrap();
+    let err: tcp::Error = serde_json::from_slice(&buf).unwrap();
+
+    assert_eq!(err.io_kind(), Some(io::ErrorKind::ConnectionReset));
+    assert!(!err.is_timeout());
+}
@@ -0,0 +1,166 @@
+mod common;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use common::{Echo, EchoClient, EchoService};
+use rpc::Server;
+use rpc::executor::{Executor, Inline, Shared, Spawner, StealingPool, Task, ThreadPool};
+use rpc::group::Address;
+use rpc::transport::tcp::{
+    Overflow,
+    TcpClientTransport,
+    TcpServerTransport,
+};
+
+#[test]
+fn elastic() {
+    let pool = StealingPool::new(1, 4).with_idle_timeout(Duration::from_millis(100));
+    let count = Arc::new(AtomicUsize::new(0));
+
+    for _ in 0..8 {
+        let count = count.clone();
+        pool.execute(Box::new(move || {
+            thread::sleep(Duration::from_millis(100));
+            count.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        })).unwrap();
+    }
+
+    // The pool grows as the workers are all busy.
+    assert_eq!(pool.get_threads(), 4);
+
+    assert_eq!(pool.shutdown(Instant::now() + Duration::from_secs(5)), 0);
+    assert_eq!(count.load(Ordering::SeqCst), 8);
+
+    // The extra workers stop once they have been idle for a while.
+    thread::sleep(Duration::from_millis(500));
+    assert_eq!(pool.get_threads(), 1);
+}
+
+#[test]
+fn stealing_server() {
+    let srv = Server::new();
+    let handle = srv.run(
+        EchoService.get_processor(),
+        TcpServerTransport::with_executor(
+            Address::from_str("127.0.0.1:0"),
+            StealingPool::new(2, 8),
+            Overflow::Block,
+        ).unwrap(),
+    ).unwrap();
+    let addr = handle.get_addr().clone();
+
+    let c = Arc::new(EchoClient::new(TcpClientTransport::new(addr)));
+
+    let reqs: Vec<_> = (0..16)
+        .map(|i| {
+            let c = c.clone();
+            thread::spawn(move || c.echo(i.to_string()).unwrap())
+        })
+        .collect();
+
+    for (i, req) in reqs.into_iter().enumerate() {
+        assert_eq!(req.join().unwrap(), i.to_string());
+    }
+}
+
+#[test]
+fn inline_server() {
+    let srv = Server::new();
+    let handle = srv.run(
+        EchoService.get_processor(),
+        TcpServerTransport::with_executor(
+            Address::from_str("127.0.0.1:0"),
+            Inline::new(),
+            Overflow::Block,
+        ).unwrap(),
+    ).unwrap();
+
+    let c = EchoClient::new(TcpClientTransport::new(handle.get_addr().clone()));
+
+    assert_eq!(c.echo(String::from("inline")).unwrap(), "inline");
+    assert_eq!(handle.shutdown(Duration::from_secs(1)).unwrap(), 0);
+}
+
+#[test]
+fn shared_pool() {
+    // Both servers run their requests on the same pool, but each of them
+    // only waits for its own requests when it is shut down.
+    let pool = Arc::new(ThreadPool::new(2));
+    let spawned = Arc::new(AtomicUsize::new(0));
+
+    let srv = Server::new();
+    let handles: Vec<_> = (0..2)
+        .map(|_| {
+            let pool = pool.clone();
+            let spawned = spawned.clone();
+            let executor = Spawner::new(move |task: Task| {
+                spawned.fetch_add(1, Ordering::SeqCst);
+                pool.execute(Box::new(move || {
+                    task();
+                    Ok(())
+                })).unwrap();
+            });
+
+            srv.run(
+                EchoService.get_processor(),
+                TcpServerTransport::with_executor(
+                    Address::from_str("127.0.0.1:0"),
+                    executor,
+                    Overflow::Block,
+                ).unwrap(),
+            ).unwrap()
+        })
+        .collect();
+
+    for (i, handle) in handles.iter().enumerate() {
+        let c = EchoClient::new(TcpClientTransport::new(handle.get_addr().clone()));
+        assert_eq!(c.echo(i.to_string()).unwrap(), i.to_string());
+    }
+
+    assert_eq!(spawned.load(Ordering::SeqCst), 2);
+
+    for handle in handles {
+        assert_eq!(handle.shutdown(Duration::from_secs(1)).unwrap(), 0);
+    }
+}
+
+#[test]
+fn shared_pool_shutdown() {
+    let pool = Arc::new(ThreadPool::new(2));
+
+    let srv = Server::new();
+    let mut handles: Vec<_> = (0..2)
+        .map(|_| {
+            srv.run(
+                EchoService.get_processor(),
+                TcpServerTransport::with_executor(
+                    Address::from_str("127.0.0.1:0"),
+                    Shared::new(pool.clone()),
+                    Overflow::Block,
+                ).unwrap(),
+            ).unwrap()
+        })
+        .collect();
+
+    let other = handles.pop().unwrap();
+    let first = handles.pop().unwrap();
+
+    let addr = first.get_addr().clone();
+    let slow = thread::spawn(move || EchoClient::new(TcpClientTransport::new(addr)).sleep(500));
+    thread::sleep(Duration::from_millis(100));
+
+    // The request in progress does not complete in time and is aborted,
+    // but only for the server being shut down.
+    assert_eq!(first.shutdown(Duration::from_millis(50)).unwrap(), 1);
+
+    let c = EchoClient::new(TcpClientTransport::new(other.get_addr().clone()));
+    for i in 0..4 {
+        assert_eq!(c.echo(i.to_string()).unwrap(), i.to_string());
+    }
+
+    slow.join().unwrap().ok();
+    assert_eq!(other.shutdown(Duration::from_secs(1)).unwrap(), 0);
+}
@@ -1,3 +1,4 @@
+use std::sync::{Arc, Mutex};
 use rpc::Server;
 use rpc::group::Address;
 use rpc::transport::tcp::{
@@ -10,9 +11,10 @@ fn hello_world() {
     #[derive(Serialize, Deserialize, Debug)]
     pub enum HelloError {
         Error(String),
-    };
+    }
 
     impl<E: std::error::Error + Sized> std::convert::From<E> for HelloError {
+        #[allow(deprecated)]
         fn from(err: E) -> Self {
             HelloError::Error(err.description().to_string())
         }
@@ -23,25 +25,44 @@ fn hello_world() {
         fn hello(&self, ctx: Context, arg: String) -> Result<String, HelloError>;
     }
 
-    let addr = Address::from_str("127.0.0.1:2000");
+    let addr = Address::from_str("127.0.0.1:0");
 
-    struct HelloService;
+    /// Service keeping the address of the peer of every request.
+    struct HelloService {
+        peers: Arc<Mutex<Vec<Address>>>,
+    }
 
     impl Hello for HelloService {
-        fn hello(&self, _: Context, arg: String) -> Result<String, HelloError> {
+        fn hello(&self, ctx: Context, arg: String) -> Result<String, HelloError> {
+            self.peers.lock().unwrap().push(ctx.get_in_addr().clone());
             Ok(arg)
         }
     }
 
-    let mut srv = Server::new();
-    let service = HelloService {};
-    srv.run(
+    let peers = Arc::new(Mutex::new(Vec::new()));
+
+    let srv = Server::new();
+    let service = HelloService { peers: Arc::clone(&peers) };
+    let handle = srv.run(
         service.get_processor(),
-        TcpServerTransport::new(addr.clone()).unwrap(),
-    );
+        TcpServerTransport::new(addr).unwrap(),
+    ).unwrap();
+    let addr = handle.get_addr().clone();
+    // The port has been chosen by the OS.
+    assert_ne!(addr.get_socket_addr().unwrap().port(), 0);
 
     let c = HelloClient::new(TcpClientTransport::new(addr));
     let msg = String::from("deadbeef");
     let r = c.hello(msg.clone()).unwrap();
     assert_eq!(msg, r);
+
+    // The second request goes through the same connection, which the
+    // server sees coming from the same port.
+    let msg = String::from("cafebabe");
+    let r = c.hello(msg.clone()).unwrap();
+    assert_eq!(msg, r);
+
+    let peers = peers.lock().unwrap();
+    assert_eq!(peers.len(), 2);
+    assert_eq!(peers[0], peers[1]);
 }
@@ -0,0 +1,128 @@
+mod common;
+
+use common::TestError;
+use std::sync::{Arc, Mutex};
+use rpc::{CallOptions, Metadata, Server};
+use rpc::group::Address;
+use rpc::interceptor::{Chain, Interceptor, Rejection};
+use rpc::metrics::Outcome;
+use rpc::transport::CallError;
+use rpc::transport::local::{LocalClientTransport, LocalServerTransport};
+use rpc::transport::tcp::{TcpClientTransport, TcpServerTransport};
+
+#[rpc_macro::service]
+trait Counter {
+    fn incr(&self, ctx: Context, v: u64) -> Result<u64, TestError>;
+    fn fail(&self, ctx: Context, v: u64) -> Result<u64, TestError>;
+}
+
+struct CounterService;
+
+impl Counter for CounterService {
+    fn incr(&self, _: Context, v: u64) -> Result<u64, TestError> {
+        Ok(v + 1)
+    }
+
+    fn fail(&self, _: Context, _: u64) -> Result<u64, TestError> {
+        Err(TestError::Error("failure".to_string()))
+    }
+}
+
+type Journal = Arc<Mutex<Vec<String>>>;
+
+/// Interceptor writing the requests it sees and their outcome in the
+/// journal under its name.
+struct Audit {
+    name: &'static str,
+    journal: Journal,
+}
+
+impl Interceptor for Audit {
+    fn before(&self, request: &'static str, _: &Context) -> Result<(), Rejection> {
+        self.journal.lock().unwrap().push(format!("{} before {}", self.name, request));
+        Ok(())
+    }
+
+    fn after(&self, request: &'static str, _: &Context, outcome: Outcome) {
+        self.journal.lock().unwrap().push(format!("{} after {} {:?}", self.name, request, outcome));
+    }
+}
+
+/// Interceptor rejecting the requests without the expected token.
+struct Auth;
+
+impl Interceptor for Auth {
+    fn before(&self, _: &'static str, ctx: &Context) -> Result<(), Rejection> {
+        match ctx.get_metadata().get("token").map(String::as_str) {
+            Some("secret") => Ok(()),
+            _ => Err(Rejection::new("invalid token")),
+        }
+    }
+}
+
+fn with_token(token: &str) -> CallOptions {
+    CallOptions {
+        metadata: Metadata::from([("token".to_string(), token.to_string())]),
+        ..CallOptions::default()
+    }
+}
+
+#[test]
+fn server_interceptors() {
+    let journal = Journal::default();
+
+    let srv = Server::new()
+        .with_interceptor(Audit { name: "audit", journal: Arc::clone(&journal) })
+        .with_interceptor(Auth)
+        .with_interceptor(Audit { name: "inner", journal: Arc::clone(&journal) });
+    let handle = srv.run(
+        CounterService.get_processor(),
+        TcpServerTransport::new(Address::from_str("127.0.0.1:0")).unwrap(),
+    ).unwrap();
+
+    let c = CounterClient::new(TcpClientTransport::new(handle.get_addr().clone()));
+
+    let (v, _) = c.incr_with_options(1, &with_token("secret")).unwrap();
+    assert_eq!(v, 2);
+    assert!(c.fail_with_options(1, &with_token("secret")).is_err());
+
+    // The rejection short-circuits the inner interceptor and the service.
+    match c.incr_with_options(1, &with_token("guess")) {
+        Err(TestError::Call(CallError::Rejected(e))) => assert_eq!(e, "invalid token"),
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    assert_eq!(*journal.lock().unwrap(), vec![
+        "audit before Incr",
+        "inner before Incr",
+        "inner after Incr Success",
+        "audit after Incr Success",
+        "audit before Fail",
+        "inner before Fail",
+        "inner after Fail Error",
+        "audit after Fail Error",
+        "audit before Incr",
+        "audit after Incr Error",
+    ]);
+}
+
+#[test]
+fn wrapped_processor() {
+    let addr = Address::Local(String::from("interceptor"));
+    let journal = Journal::default();
+
+    // The chain of the server comes before the one of the processor.
+    let chain = Chain::new().with(Audit { name: "processor", journal: Arc::clone(&journal) });
+    let srv = Server::new().with_interceptor(Audit { name: "server", journal: Arc::clone(&journal) });
+    let _handle = srv.run(chain.wrap(CounterService.get_processor()), LocalServerTransport::new(addr.clone())).unwrap();
+
+    let c = CounterClient::new(LocalClientTransport::new(addr));
+    assert_eq!(c.incr(41).unwrap(), 42);
+
+    assert_eq!(*journal.lock().unwrap(), vec![
+        "server before Incr",
+        "processor before Incr",
+        "processor after Incr Success",
+        "server after Incr Success",
+    ]);
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use common::{Echo, EchoClient, EchoService, TestError};
+use rpc::transport::CallError;
+use rpc::Server;
+use rpc::group::Address;
+use rpc::transport::tcp::{
+    PoolConfig,
+    TcpClientTransport,
+    TcpServerTransport,
+};
+
+#[test]
+fn request_too_large() {
+    let srv = Server::new();
+    let handle = srv.run(
+        EchoService.get_processor(),
+        TcpServerTransport::new(Address::from_str("127.0.0.1:0"))
+            .unwrap()
+            .with_max_message_size(1024),
+    ).unwrap();
+
+    let c = EchoClient::new(TcpClientTransport::new(handle.get_addr().clone()));
+
+    match c.echo("a".repeat(4096)) {
+        Err(TestError::Call(CallError::MessageTooLarge)) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    // The connection can still be used by the next requests.
+    assert_eq!(c.echo(String::from("small")).unwrap(), "small");
+}
+
+#[test]
+fn reply_too_large() {
+    let srv = Server::new();
+    let handle = srv.run(
+        EchoService.get_processor(),
+        TcpServerTransport::new(Address::from_str("127.0.0.1:0")).unwrap(),
+    ).unwrap();
+
+    let config = PoolConfig {
+        max_message_size: 64,
+        ..PoolConfig::default()
+    };
+    let c = EchoClient::new(TcpClientTransport::with_pool_config(handle.get_addr().clone(), config));
+
+    match c.echo("a".repeat(128)) {
+        Err(TestError::Call(CallError::MessageTooLarge)) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    assert_eq!(c.echo(String::from("small")).unwrap(), "small");
+}
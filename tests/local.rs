@@ -0,0 +1,43 @@
+mod common;
+
+use common::TestError;
+use rpc::Server;
+use rpc::group::Address;
+use rpc::transport::local::{
+    LocalClientTransport,
+    LocalServerTransport,
+};
+
+#[test]
+fn local() {
+    #[rpc_macro::service]
+    trait Echo {
+        fn echo(&self, ctx: Context, arg: String) -> Result<String, TestError>;
+        fn whoami(&self, ctx: Context, arg: ()) -> Result<String, TestError>;
+    }
+
+    struct EchoService;
+
+    impl Echo for EchoService {
+        fn echo(&self, _: Context, arg: String) -> Result<String, TestError> {
+            Ok(arg)
+        }
+
+        fn whoami(&self, ctx: Context, _: ()) -> Result<String, TestError> {
+            Ok(ctx.get_in_addr().to_string())
+        }
+    }
+
+    let addr = Address::Local(String::from("echo"));
+
+    let srv = Server::new();
+    let _handle = srv.run(EchoService.get_processor(), LocalServerTransport::new(addr.clone())).unwrap();
+
+    let c = EchoClient::new(LocalClientTransport::new(addr));
+    assert_eq!(c.echo(String::from("deadbeef")).unwrap(), "deadbeef");
+    assert!(c.whoami(()).unwrap().starts_with("local-client-"));
+
+    // Nothing is registered under this name.
+    let c = EchoClient::new(LocalClientTransport::new(Address::from_str("unknown")));
+    assert!(c.echo(String::new()).is_err());
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use common::TestError;
+use rpc::{CallOptions, Metadata, Server};
+use rpc::group::Address;
+use rpc::transport::local::{LocalClientTransport, LocalServerTransport};
+use rpc::transport::tcp::{TcpClientTransport, TcpServerTransport};
+
+#[rpc_macro::service]
+trait Lookup {
+    fn lookup(&self, ctx: Context, key: String) -> Result<Option<String>, TestError>;
+}
+
+struct LookupService;
+
+impl Lookup for LookupService {
+    /// Return the value of the key in the metadata of the request and echo
+    /// the trace identifier in the metadata of the reply.
+    fn lookup(&self, ctx: Context, key: String) -> Result<Option<String>, TestError> {
+        if let Some(trace) = ctx.get_metadata().get("trace-id") {
+            ctx.set_reply_metadata("trace-id", trace.clone());
+        }
+
+        Ok(ctx.get_metadata().get(&key).cloned())
+    }
+}
+
+fn metadata(entries: &[(&str, &str)]) -> Metadata {
+    entries.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+}
+
+fn assert_metadata<T: rpc::transport::ClientTransport<ClientData, ServerData>>(c: &LookupClient<T>)
+where
+    T::Error: 'static,
+{
+    assert_eq!(c.lookup("tenant".to_string()).unwrap(), Some("acme".to_string()));
+    assert_eq!(c.lookup("trace-id".to_string()).unwrap(), None);
+
+    // The metadata of the call takes precedence over the one of the client.
+    let opts = CallOptions {
+        metadata: metadata(&[("tenant", "other"), ("trace-id", "abc")]),
+        ..CallOptions::default()
+    };
+    let (value, reply) = c.lookup_with_options("tenant".to_string(), &opts).unwrap();
+    assert_eq!(value, Some("other".to_string()));
+    assert_eq!(reply, metadata(&[("trace-id", "abc")]));
+
+    // The metadata of the reply is not carried over to the next request.
+    let (_, reply) = c.lookup_with_options("tenant".to_string(), &CallOptions::default()).unwrap();
+    assert!(reply.is_empty());
+}
+
+#[test]
+fn tcp_metadata() {
+    let srv = Server::new();
+    let handle = srv.run(
+        LookupService.get_processor(),
+        TcpServerTransport::new(Address::from_str("127.0.0.1:0")).unwrap(),
+    ).unwrap();
+
+    let c = LookupClient::new(TcpClientTransport::new(handle.get_addr().clone()))
+        .with_metadata(metadata(&[("tenant", "acme")]));
+
+    assert_metadata(&c);
+}
+
+#[test]
+fn local_metadata() {
+    let addr = Address::Local(String::from("metadata"));
+
+    let srv = Server::new();
+    let _handle = srv.run(LookupService.get_processor(), LocalServerTransport::new(addr.clone())).unwrap();
+
+    let c = LookupClient::new(LocalClientTransport::new(addr))
+        .with_metadata(metadata(&[("tenant", "acme")]));
+
+    assert_metadata(&c);
+}
@@ -0,0 +1,159 @@
+mod common;
+
+use common::TestError;
+use std::sync::Arc;
+use rpc::Server;
+use rpc::group::Address;
+use rpc::metrics::{self, Registry};
+use rpc::transport::tcp::{
+    TcpClientTransport,
+    TcpServerTransport,
+};
+
+#[test]
+fn prometheus() {
+    #[rpc_macro::service]
+    trait Hello {
+        fn hello(&self, ctx: Context, arg: String) -> Result<String, TestError>;
+        fn crash(&self, ctx: Context, arg: String) -> Result<String, TestError>;
+    }
+
+    struct HelloService;
+
+    impl Hello for HelloService {
+        fn hello(&self, _: Context, arg: String) -> Result<String, TestError> {
+            if arg.is_empty() {
+                return Err(TestError::Empty);
+            }
+
+            Ok(arg)
+        }
+
+        fn crash(&self, _: Context, _: String) -> Result<String, TestError> {
+            panic!("crash");
+        }
+    }
+
+    let registry = Arc::new(Registry::new());
+    metrics::set_sink(registry.clone());
+
+    let srv = Server::new();
+    let handle = srv.run(
+        HelloService.get_processor(),
+        TcpServerTransport::new(Address::from_str("127.0.0.1:0")).unwrap(),
+    ).unwrap();
+    let addr = handle.get_addr().clone();
+
+    let c = HelloClient::new(TcpClientTransport::new(addr.clone()));
+    assert_eq!(c.hello(String::from("deadbeef")).unwrap(), "deadbeef");
+    assert!(c.hello(String::new()).is_err());
+    assert!(c.crash(String::new()).is_err());
+
+    // A second server reports to the same sink under its own address.
+    let other = srv.run(
+        HelloService.get_processor(),
+        TcpServerTransport::new(Address::from_str("127.0.0.1:0")).unwrap(),
+    ).unwrap();
+    let other_addr = other.get_addr().clone();
+
+    let c = HelloClient::new(TcpClientTransport::new(other_addr.clone()));
+    assert_eq!(c.hello(String::from("deadbeef")).unwrap(), "deadbeef");
+
+    handle.shutdown(std::time::Duration::from_secs(5)).unwrap();
+    other.shutdown(std::time::Duration::from_secs(5)).unwrap();
+
+    let out = registry.render();
+    assert!(out.contains("# TYPE rpc_requests_total counter"));
+    assert!(out.contains("rpc_requests_total{service=\"Hello\",method=\"hello\"} 3"));
+    assert!(out.contains("rpc_request_errors_total{service=\"Hello\",method=\"hello\"} 1"));
+    assert!(out.contains("rpc_request_panics_total{service=\"Hello\",method=\"hello\"} 0"));
+    assert!(out.contains("rpc_requests_total{service=\"Hello\",method=\"crash\"} 1"));
+    assert!(out.contains("rpc_request_panics_total{service=\"Hello\",method=\"crash\"} 1"));
+    assert!(out.contains("rpc_request_duration_seconds_bucket{service=\"Hello\",method=\"hello\",le=\"+Inf\"} 3"));
+    assert!(out.contains("rpc_request_duration_seconds_count{service=\"Hello\",method=\"hello\"} 3"));
+    assert!(out.contains("# TYPE rpc_queue_depth gauge"));
+
+    // The transport is measured for each server.
+    for server in [&addr, &other_addr] {
+        assert!(out.contains(&format!("rpc_queue_depth{{server=\"{}\"}} 0", server)));
+        assert!(out.contains(&format!("rpc_connections_accepted_total{{server=\"{}\"}} 1", server)));
+        assert!(server_value(&out, "rpc_received_bytes_total", server) > 0);
+        assert!(server_value(&out, "rpc_sent_bytes_total", server) > 0);
+    }
+    assert!(server_value(&out, "rpc_received_bytes_total", &addr) > server_value(&out, "rpc_received_bytes_total", &other_addr));
+}
+
+/// Value of the measurement of the server in the rendered registry.
+fn server_value(out: &str, name: &str, server: &Address) -> u64 {
+    let prefix = format!("{}{{server=\"{}\"}} ", name, server);
+    let line = out.lines().find(|l| l.starts_with(&prefix)).unwrap();
+
+    line[prefix.len()..].parse().unwrap()
+}
+
+#[test]
+fn server_sink() {
+    #[rpc_macro::service]
+    trait Echo {
+        fn echo(&self, ctx: Context, arg: String) -> Result<String, TestError>;
+    }
+
+    struct EchoService;
+
+    impl Echo for EchoService {
+        fn echo(&self, _: Context, arg: String) -> Result<String, TestError> {
+            Ok(arg)
+        }
+    }
+
+    let registry = Arc::new(Registry::new());
+
+    let srv = Server::new();
+    let handle = srv.run(
+        EchoService.get_processor(),
+        TcpServerTransport::new(Address::from_str("127.0.0.1:0")).unwrap().with_metrics(registry.clone()),
+    ).unwrap();
+    let addr = handle.get_addr().clone();
+
+    let c = EchoClient::new(TcpClientTransport::new(addr.clone()));
+    assert_eq!(c.echo(String::from("deadbeef")).unwrap(), "deadbeef");
+
+    handle.shutdown(std::time::Duration::from_secs(5)).unwrap();
+
+    // Only the measurements of this server are in its sink.
+    let out = registry.render();
+    assert!(out.contains("rpc_requests_total{service=\"Echo\",method=\"echo\"} 1"));
+    assert!(!out.contains("service=\"Hello\""));
+    assert!(out.contains(&format!("rpc_queue_depth{{server=\"{}\"}} 0", addr)));
+    assert!(out.contains(&format!("rpc_connections_accepted_total{{server=\"{}\"}} 1", addr)));
+}
+
+#[cfg(unix)]
+#[test]
+fn escaped_labels() {
+    use common::{Echo, EchoClient, EchoService};
+    use rpc::transport::unix::{UnixClientTransport, UnixServerTransport};
+
+    let registry = Arc::new(Registry::new());
+
+    // The path of a Unix domain socket can contain the characters that
+    // delimit a label.
+    let name = format!("rpc-test-\"metrics\\{}\n.sock", std::process::id());
+    let addr = Address::Unix(std::env::temp_dir().join(name));
+
+    let srv = Server::new();
+    let handle = srv.run(
+        EchoService.get_processor(),
+        UnixServerTransport::new(addr.clone()).unwrap().with_metrics(registry.clone()),
+    ).unwrap();
+
+    let c = EchoClient::new(UnixClientTransport::new(addr.clone()));
+    assert_eq!(c.echo(String::from("deadbeef")).unwrap(), "deadbeef");
+
+    handle.shutdown(std::time::Duration::from_secs(5)).unwrap();
+
+    let server = addr.to_string().replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+    let out = registry.render();
+    assert!(out.contains(&format!("rpc_connections_accepted_total{{server=\"{}\"}} 1", server)));
+    assert!(out.lines().all(|l| l.starts_with('#') || l.starts_with("rpc_")));
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use common::{Echo, EchoClient, EchoService};
+use rpc::Server;
+use rpc::group::Address;
+use rpc::transport::tcp::{
+    PoolConfig,
+    TcpClientTransport,
+    TcpServerTransport,
+};
+
+#[test]
+fn out_of_order() {
+    let addr = Address::from_str("127.0.0.1:0");
+
+    let srv = Server::new();
+    let handle = srv.run(
+        EchoService.get_processor(),
+        TcpServerTransport::new(addr).unwrap(),
+    ).unwrap();
+    let addr = handle.get_addr().clone();
+
+    // A single connection is shared by all the requests.
+    let config = PoolConfig {
+        max_idle: 1,
+        ..PoolConfig::default()
+    };
+    let c = Arc::new(EchoClient::new(TcpClientTransport::with_pool_config(addr, config)));
+
+    let slow = {
+        let c = Arc::clone(&c);
+        std::thread::spawn(move || {
+            assert_eq!(c.sleep(500).unwrap(), 500);
+            Instant::now()
+        })
+    };
+
+    std::thread::sleep(Duration::from_millis(100));
+    assert_eq!(c.sleep(0).unwrap(), 0);
+    let fast = Instant::now();
+
+    // The fast reply came back before the slow one.
+    assert!(fast < slow.join().unwrap());
+}
+
+#[test]
+fn long_request() {
+    let srv = Server::new();
+    let handle = srv.run(
+        EchoService.get_processor(),
+        TcpServerTransport::new(Address::from_str("127.0.0.1:0"))
+            .unwrap()
+            .with_read_timeout(Some(Duration::from_millis(200))),
+    ).unwrap();
+
+    let config = PoolConfig {
+        max_idle: 1,
+        ..PoolConfig::default()
+    };
+    let c = Arc::new(EchoClient::new(TcpClientTransport::with_pool_config(handle.get_addr().clone(), config)));
+
+    let slow = {
+        let c = Arc::clone(&c);
+        std::thread::spawn(move || {
+            assert_eq!(c.sleep(800).unwrap(), 800);
+            Instant::now()
+        })
+    };
+
+    // The connection has been quiet for longer than the read timeout but
+    // the server keeps reading it as a request is still in flight.
+    std::thread::sleep(Duration::from_millis(400));
+    assert_eq!(c.sleep(0).unwrap(), 0);
+    let fast = Instant::now();
+
+    assert!(fast < slow.join().unwrap());
+}
@@ -0,0 +1,160 @@
+mod common;
+
+use std::thread;
+use std::time::{Duration, Instant};
+use common::{Echo, EchoClient, EchoService, TestError};
+use rpc::transport::CallError;
+use rpc::{CallOptions, Server};
+use rpc::group::Address;
+use rpc::transport::tcp::{
+    Overflow,
+    PoolConfig,
+    TcpClientTransport,
+    TcpServerTransport,
+    WorkerConfig,
+};
+
+/// Send three requests to a server with a single worker and room for a
+/// single request in its queue, and return their results. The requests are
+/// sent with the deadline when there is one.
+fn run_with_overflow(overflow: Overflow, client: PoolConfig, deadline: Option<Duration>) -> Vec<Result<u64, TestError>> {
+    let config = WorkerConfig {
+        workers: 1,
+        max_queued: Some(1),
+        overflow,
+    };
+
+    let srv = Server::new();
+    let handle = srv.run(
+        EchoService.get_processor(),
+        TcpServerTransport::with_worker_config(Address::from_str("127.0.0.1:0"), config).unwrap(),
+    ).unwrap();
+    let addr = handle.get_addr().clone();
+
+    let c = std::sync::Arc::new(EchoClient::new(TcpClientTransport::with_pool_config(addr, client)));
+
+    let reqs: Vec<_> = (0..3)
+        .map(|_| {
+            let c = c.clone();
+            let opts = CallOptions {
+                deadline: deadline.map(|d| Instant::now() + d),
+                ..CallOptions::default()
+            };
+            let req = thread::spawn(move || c.sleep_with_options(200, &opts).map(|(ms, _)| ms));
+            // Let the request reach the server before the next one.
+            thread::sleep(Duration::from_millis(50));
+            req
+        })
+        .collect();
+
+    reqs.into_iter().map(|req| req.join().unwrap()).collect()
+}
+
+/// Configuration of a client giving up on the requests after 2 seconds, as a
+/// dropped request is only noticed when the call times out.
+fn with_call_timeout() -> PoolConfig {
+    PoolConfig {
+        call_timeout: Some(Duration::from_secs(2)),
+        ..PoolConfig::default()
+    }
+}
+
+#[test]
+fn reject() {
+    let res = run_with_overflow(Overflow::Reject, with_call_timeout(), None);
+
+    assert_eq!(res[0].as_ref().unwrap(), &200);
+    assert_eq!(res[1].as_ref().unwrap(), &200);
+    match &res[2] {
+        Err(TestError::Call(CallError::ServerBusy)) => (),
+        res => panic!("unexpected result {:?}", res),
+    }
+}
+
+#[test]
+fn block() {
+    let res = run_with_overflow(Overflow::Block, with_call_timeout(), None);
+
+    for r in res {
+        assert_eq!(r.unwrap(), 200);
+    }
+}
+
+#[test]
+fn drop() {
+    // The client gives up either with the call timeout of its pool or with
+    // the deadline of the call, without which it would wait forever.
+    let runs = vec![
+        run_with_overflow(Overflow::Drop, with_call_timeout(), None),
+        run_with_overflow(Overflow::Drop, PoolConfig::default(), Some(Duration::from_secs(2))),
+    ];
+
+    for res in runs {
+        // The requests already accepted on the connection are still answered.
+        assert_eq!(res[0].as_ref().unwrap(), &200);
+        assert_eq!(res[1].as_ref().unwrap(), &200);
+        match &res[2] {
+            Err(TestError::Call(CallError::TimedOut)) => (),
+            res => panic!("unexpected result {:?}", res),
+        }
+    }
+}
+
+#[test]
+fn max_connections() {
+    let srv = Server::new();
+    let handle = srv.run(
+        EchoService.get_processor(),
+        TcpServerTransport::new(Address::from_str("127.0.0.1:0"))
+            .unwrap()
+            .with_max_connections(1),
+    ).unwrap();
+    let addr = handle.get_addr().clone();
+
+    let first = EchoClient::new(TcpClientTransport::new(addr.clone()));
+    assert_eq!(first.sleep(0).unwrap(), 0);
+
+    // The connection of the first client is kept open by its pool.
+    let second = EchoClient::new(TcpClientTransport::new(addr));
+    assert!(second.sleep(0).is_err());
+
+    // The thread of the connection is released once the client is gone.
+    std::mem::drop(first);
+
+    let mut res = second.sleep(0);
+    for _ in 0..20 {
+        if res.is_ok() {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(50));
+        res = second.sleep(0);
+    }
+    assert_eq!(res.unwrap(), 0);
+}
+
+#[test]
+fn no_worker() {
+    let config = WorkerConfig {
+        workers: 0,
+        ..WorkerConfig::default()
+    };
+
+    match TcpServerTransport::with_worker_config(Address::from_str("127.0.0.1:0"), config) {
+        Err(e) => assert_eq!(e.kind(), std::io::ErrorKind::InvalidInput),
+        Ok(_) => panic!("a pool without workers is accepted"),
+    }
+}
+
+#[test]
+fn empty_queue() {
+    let config = WorkerConfig {
+        max_queued: Some(0),
+        ..WorkerConfig::default()
+    };
+
+    match TcpServerTransport::with_worker_config(Address::from_str("127.0.0.1:0"), config) {
+        Err(e) => assert_eq!(e.kind(), std::io::ErrorKind::InvalidInput),
+        Ok(_) => panic!("a queue without room is accepted"),
+    }
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+use common::{Echo, EchoClient, EchoService};
+use rpc::Server;
+use rpc::group::Address;
+use rpc::transport::tcp::{
+    PoolConfig,
+    TcpClientTransport,
+    TcpServerTransport,
+};
+
+#[test]
+fn shared_client() {
+    let addr = Address::from_str("127.0.0.1:0");
+
+    let srv = Server::new();
+    let handle = srv.run(
+        EchoService.get_processor(),
+        TcpServerTransport::new(addr).unwrap(),
+    ).unwrap();
+    let addr = handle.get_addr().clone();
+
+    let config = PoolConfig {
+        max_idle: 2,
+        idle_timeout: Duration::from_millis(200),
+        ..PoolConfig::default()
+    };
+    let c = Arc::new(EchoClient::new(TcpClientTransport::with_pool_config(addr, config)));
+
+    let mut threads = Vec::new();
+    for i in 0..4 {
+        let c = Arc::clone(&c);
+        threads.push(std::thread::spawn(move || {
+            for j in 0..10 {
+                let msg = (i * j).to_string();
+                assert_eq!(c.echo(msg.clone()).unwrap(), msg);
+            }
+        }));
+    }
+
+    for th in threads {
+        th.join().unwrap();
+    }
+
+    // Idle connections have expired and are replaced transparently.
+    std::thread::sleep(Duration::from_millis(300));
+    assert_eq!(c.echo(String::from("42")).unwrap(), "42");
+}
@@ -0,0 +1,75 @@
+mod common;
+
+use std::time::{Duration, Instant};
+use common::{Echo, EchoClient, EchoService};
+use rpc::Server;
+use rpc::group::Address;
+use rpc::transport::tcp::{
+    TcpClientTransport,
+    TcpServerTransport,
+};
+
+#[test]
+fn shutdown() {
+    // The request in progress is drained before the server stops.
+    let addr = Address::from_str("127.0.0.1:0");
+
+    let srv = Server::new();
+    let handle = srv.run(
+        EchoService.get_processor(),
+        TcpServerTransport::new(addr).unwrap(),
+    ).unwrap();
+    let addr = handle.get_addr().clone();
+
+    let c = EchoClient::new(TcpClientTransport::new(addr.clone()));
+    let req = std::thread::spawn(move || c.sleep(300).unwrap());
+
+    std::thread::sleep(Duration::from_millis(100));
+    assert_eq!(handle.shutdown(Duration::from_secs(5)).unwrap(), 0);
+    assert_eq!(req.join().unwrap(), 300);
+
+    // New connections are refused.
+    let c = EchoClient::new(TcpClientTransport::new(addr));
+    assert!(c.sleep(0).is_err());
+
+    // The request taking longer than the deadline is aborted.
+    let addr = Address::from_str("127.0.0.1:0");
+
+    let srv = Server::new();
+    let handle = srv.run(
+        EchoService.get_processor(),
+        TcpServerTransport::new(addr).unwrap(),
+    ).unwrap();
+    let addr = handle.get_addr().clone();
+
+    let c = EchoClient::new(TcpClientTransport::new(addr));
+    let req = std::thread::spawn(move || c.sleep(2000).is_err());
+
+    std::thread::sleep(Duration::from_millis(100));
+    let now = Instant::now();
+    assert_eq!(handle.shutdown(Duration::from_millis(100)).unwrap(), 1);
+    assert!(now.elapsed() < Duration::from_millis(1000));
+    assert!(req.join().unwrap());
+}
+
+#[test]
+fn addr_in_use() {
+    let addr = Address::from_str("127.0.0.1:0");
+
+    let srv = Server::new();
+    let handle = srv.run(
+        EchoService.get_processor(),
+        TcpServerTransport::new(addr).unwrap(),
+    ).unwrap();
+    let addr = handle.get_addr().clone();
+
+    // The address is already used by the first server.
+    let res = srv.run(
+        EchoService.get_processor(),
+        TcpServerTransport::new(addr).unwrap(),
+    );
+    match res {
+        Err(e) => assert_eq!(e.io_kind(), Some(std::io::ErrorKind::AddrInUse)),
+        Ok(_) => panic!("the address is bound twice"),
+    }
+}
@@ -0,0 +1,164 @@
+#![cfg(feature = "tls")]
+
+mod common;
+
+use common::TestError;
+use rcgen::{BasicConstraints, Certificate, CertificateParams, DistinguishedName, DnType, IsCa, SanType};
+use rpc::Server;
+use rpc::group::Address;
+use rpc::transport::tls::{
+    TlsClientConfig,
+    TlsClientTransport,
+    TlsServerConfig,
+    TlsServerTransport,
+};
+use rustls::server::AllowAnyAnonymousOrAuthenticatedClient;
+use std::sync::Arc;
+
+fn authority() -> Certificate {
+    let mut params = CertificateParams::new(vec![]);
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+
+    Certificate::from_params(params).unwrap()
+}
+
+#[test]
+fn tls() {
+    #[rpc_macro::service]
+    trait WhoAmI {
+        fn whoami(&self, ctx: Context, arg: ()) -> Result<Vec<u8>, TestError>;
+    }
+
+    struct WhoAmIService;
+
+    impl WhoAmI for WhoAmIService {
+        fn whoami(&self, ctx: Context, _: ()) -> Result<Vec<u8>, TestError> {
+            match ctx.get_peer_certificates() {
+                Some(certs) => Ok(certs[0].clone()),
+                None => Err(TestError::Empty),
+            }
+        }
+    }
+
+    let ca = authority();
+    let ca_pem = ca.serialize_pem().unwrap();
+
+    let mut params = CertificateParams::new(vec![]);
+    params.subject_alt_names = vec![SanType::IpAddress("127.0.0.1".parse().unwrap())];
+    let server = Certificate::from_params(params).unwrap();
+
+    let client = Certificate::from_params(CertificateParams::new(vec!["alice".into()])).unwrap();
+    let client_pem = client.serialize_pem_with_signer(&ca).unwrap();
+    let client_der = rustls_pemfile::certs(&mut client_pem.as_bytes()).unwrap().remove(0);
+
+    // Client certificates are accepted but not required.
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add(&rustls::Certificate(ca.serialize_der().unwrap())).unwrap();
+    let key = rustls::PrivateKey(server.serialize_private_key_der());
+    let cert = rustls::Certificate(server.serialize_der_with_signer(&ca).unwrap());
+    let config = rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_client_cert_verifier(AllowAnyAnonymousOrAuthenticatedClient::new(roots).boxed())
+        .with_single_cert(vec![cert], key)
+        .unwrap();
+
+    let addr = Address::from_str("127.0.0.1:0");
+
+    let srv = Server::new();
+    let handle = srv.run(
+        WhoAmIService.get_processor(),
+        TlsServerTransport::new(addr, TlsServerConfig::from(Arc::new(config))).unwrap(),
+    ).unwrap();
+    let addr = handle.get_addr().clone();
+
+    let config = TlsClientConfig::with_identity(
+        ca_pem.as_bytes(),
+        client_pem.as_bytes(),
+        client.serialize_private_key_pem().as_bytes(),
+    ).unwrap();
+    let c = WhoAmIClient::new(TlsClientTransport::new(addr.clone(), config));
+    assert_eq!(c.whoami(()).unwrap(), client_der);
+
+    let config = TlsClientConfig::new(ca_pem.as_bytes()).unwrap();
+    let c = WhoAmIClient::new(TlsClientTransport::new(addr.clone(), config));
+    match c.whoami(()) {
+        Err(TestError::Empty) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    // The server is not trusted by a client using another authority.
+    let config = TlsClientConfig::new(authority().serialize_pem().unwrap().as_bytes()).unwrap();
+    let c = WhoAmIClient::new(TlsClientTransport::new(addr, config));
+    assert!(c.whoami(()).is_err());
+}
+
+#[test]
+fn mutual_tls() {
+    #[rpc_macro::service]
+    trait WhoAmI {
+        fn whoami(&self, ctx: Context, arg: ()) -> Result<(String, Vec<String>), TestError>;
+    }
+
+    struct WhoAmIService;
+
+    impl WhoAmI for WhoAmIService {
+        fn whoami(&self, ctx: Context, _: ()) -> Result<(String, Vec<String>), TestError> {
+            match ctx.get_peer_identity() {
+                Some(id) => Ok((id.get_subject().to_string(), id.get_alt_names().to_vec())),
+                None => Err(TestError::Empty),
+            }
+        }
+    }
+
+    let ca = authority();
+    let ca_pem = ca.serialize_pem().unwrap();
+
+    let mut params = CertificateParams::new(vec![]);
+    params.subject_alt_names = vec![SanType::IpAddress("127.0.0.1".parse().unwrap())];
+    let server = Certificate::from_params(params).unwrap();
+    let config = TlsServerConfig::with_client_ca(
+        server.serialize_pem_with_signer(&ca).unwrap().as_bytes(),
+        server.serialize_private_key_pem().as_bytes(),
+        ca_pem.as_bytes(),
+    ).unwrap();
+
+    let addr = Address::from_str("127.0.0.1:0");
+
+    let srv = Server::new();
+    let handle = srv.run(
+        WhoAmIService.get_processor(),
+        TlsServerTransport::new(addr, config).unwrap(),
+    ).unwrap();
+    let addr = handle.get_addr().clone();
+
+    let mut params = CertificateParams::new(vec!["alice.example".into()]);
+    params.subject_alt_names.push(SanType::IpAddress("10.0.0.1".parse().unwrap()));
+    params.distinguished_name = DistinguishedName::new();
+    params.distinguished_name.push(DnType::CommonName, "alice");
+    let client = Certificate::from_params(params).unwrap();
+
+    let config = TlsClientConfig::with_identity(
+        ca_pem.as_bytes(),
+        client.serialize_pem_with_signer(&ca).unwrap().as_bytes(),
+        client.serialize_private_key_pem().as_bytes(),
+    ).unwrap();
+    let c = WhoAmIClient::new(TlsClientTransport::new(addr.clone(), config));
+    let (subject, alt_names) = c.whoami(()).unwrap();
+    assert_eq!(subject, "CN=alice");
+    assert_eq!(alt_names, vec!["alice.example", "10.0.0.1"]);
+
+    // Clients without a certificate are rejected during the handshake.
+    let config = TlsClientConfig::new(ca_pem.as_bytes()).unwrap();
+    let c = WhoAmIClient::new(TlsClientTransport::new(addr.clone(), config));
+    assert!(c.whoami(()).is_err());
+
+    // Certificates issued by another authority are rejected as well.
+    let other = authority();
+    let config = TlsClientConfig::with_identity(
+        ca_pem.as_bytes(),
+        client.serialize_pem_with_signer(&other).unwrap().as_bytes(),
+        client.serialize_private_key_pem().as_bytes(),
+    ).unwrap();
+    let c = WhoAmIClient::new(TlsClientTransport::new(addr, config));
+    assert!(c.whoami(()).is_err());
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use std::io;
+use std::sync::{Arc, Mutex};
+use common::{Echo, EchoClient, EchoService};
+use rpc::Server;
+use rpc::group::Address;
+use rpc::transport::tcp::{
+    TcpClientTransport,
+    TcpServerTransport,
+};
+
+/// Writer keeping the logs in memory so that they can be checked.
+#[derive(Clone)]
+struct Logs(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for Logs {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn request_spans() {
+    let logs = Logs(Arc::new(Mutex::new(Vec::new())));
+    let writer = logs.clone();
+    tracing_subscriber::fmt()
+        .with_writer(move || writer.clone())
+        .with_ansi(false)
+        .init();
+
+    let srv = Server::new();
+    let handle = srv.run(
+        EchoService.get_processor(),
+        TcpServerTransport::new(Address::from_str("127.0.0.1:0")).unwrap(),
+    ).unwrap();
+    let addr = handle.get_addr().clone();
+
+    let c = EchoClient::new(TcpClientTransport::new(addr));
+    assert_eq!(c.echo(String::from("deadbeef")).unwrap(), "deadbeef");
+    assert!(c.echo(String::new()).is_err());
+
+    let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+    assert!(logs.contains("server has started"));
+
+    let lines: Vec<&str> = logs.lines().filter(|l| l.contains("request{method=\"echo\"")).collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].contains("request succeeded"));
+    assert!(lines[1].contains("request failed") && lines[1].contains("error=Empty"));
+}
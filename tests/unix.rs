@@ -0,0 +1,77 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::{Echo, EchoClient, EchoService, TestError};
+use rpc::Server;
+use rpc::group::Address;
+use rpc::transport::CallError;
+use rpc::transport::unix::{
+    UnixClientTransport,
+    UnixServerTransport,
+    WorkerConfig,
+};
+
+#[test]
+fn unix_socket() {
+    let path = std::env::temp_dir().join(format!("rpc-test-{}.sock", std::process::id()));
+    let addr = Address::from_str(&format!("unix:{}", path.display()));
+    assert_eq!(addr, Address::Unix(path.clone()));
+
+    {
+        let srv = Server::new();
+        let _handle = srv.run(
+            EchoService.get_processor(),
+            UnixServerTransport::new(addr.clone()).unwrap(),
+        ).unwrap();
+
+        let c = EchoClient::new(UnixClientTransport::new(addr));
+        let msg = String::from("deadbeef");
+        assert_eq!(c.echo(msg.clone()).unwrap(), msg);
+    }
+
+    // The socket file is removed when the server is closed.
+    assert!(!path.exists());
+}
+
+#[test]
+fn unix_limits() {
+    let path = std::env::temp_dir().join(format!("rpc-test-limits-{}.sock", std::process::id()));
+    let addr = Address::Unix(path);
+
+    let srv = Server::new();
+    let _handle = srv.run(
+        EchoService.get_processor(),
+        UnixServerTransport::with_worker_config(addr.clone(), WorkerConfig { workers: 1, ..WorkerConfig::default() })
+            .unwrap()
+            .with_max_message_size(1024)
+            .with_max_connections(1),
+    ).unwrap();
+
+    let first = EchoClient::new(UnixClientTransport::new(addr.clone()));
+    match first.echo("a".repeat(4096)) {
+        Err(TestError::Call(CallError::MessageTooLarge)) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+    assert_eq!(first.echo(String::from("small")).unwrap(), "small");
+
+    // The connection of the first client is kept open by its pool.
+    let second = EchoClient::new(UnixClientTransport::new(addr));
+    assert!(second.sleep(0).is_err());
+}
+
+#[test]
+fn socket_file_removed() {
+    let path = std::env::temp_dir().join(format!("rpc-test-removed-{}.sock", std::process::id()));
+    let addr = Address::Unix(path.clone());
+
+    let srv = Server::new();
+    let handle = srv.run(
+        EchoService.get_processor(),
+        UnixServerTransport::new(addr).unwrap(),
+    ).unwrap();
+
+    // The server still shuts down cleanly when the file is already gone.
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(handle.shutdown(std::time::Duration::from_secs(1)).unwrap(), 0);
+}
@@ -0,0 +1,133 @@
+mod common;
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use common::{Echo, EchoClient, EchoService, TestError};
+use rpc::Server;
+use rpc::group::Address;
+use rpc::transport::CallError;
+use rpc::transport::tcp::{Error, TcpClientTransport, TcpServerTransport};
+
+/// Peer speaking the protocol written by hand so that it can announce any
+/// version, as the transports of the crate only speak theirs.
+struct RawPeer {
+    stream: TcpStream,
+}
+
+impl RawPeer {
+    /// Open a connection announcing the version and JSON, and return the
+    /// welcome of the server.
+    fn connect(addr: &Address, version: u16) -> (RawPeer, Vec<u8>) {
+        let mut peer = RawPeer {
+            stream: TcpStream::connect(addr.get_socket_addr().unwrap()).unwrap(),
+        };
+
+        let mut hello = b"RPC\0".to_vec();
+        hello.extend_from_slice(&version.to_be_bytes());
+        hello.extend_from_slice(&[1, 4]);
+        hello.extend_from_slice(b"json");
+        peer.write_frame(0, &hello);
+
+        let (_, welcome) = peer.read_frame().unwrap();
+
+        (peer, welcome)
+    }
+
+    fn write_frame(&mut self, id: u64, payload: &[u8]) {
+        let mut frame = (payload.len() as u32).to_be_bytes().to_vec();
+        frame.extend_from_slice(&id.to_be_bytes());
+        frame.extend_from_slice(payload);
+
+        self.stream.write_all(&frame).unwrap();
+    }
+
+    /// Read the next frame, or return None when the peer closed the
+    /// connection.
+    fn read_frame(&mut self) -> Option<(u64, Vec<u8>)> {
+        let mut header = [0u8; 12];
+        self.stream.read_exact(&mut header).ok()?;
+
+        let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let mut id = [0u8; 8];
+        id.copy_from_slice(&header[4..]);
+
+        let mut payload = vec![0u8; len];
+        self.stream.read_exact(&mut payload).ok()?;
+
+        Some((u64::from_be_bytes(id), payload))
+    }
+
+    /// Send the request and return the payload of its reply.
+    fn call(&mut self, id: u64, request: &[u8]) -> Vec<u8> {
+        self.write_frame(id, request);
+
+        let (reply_id, reply) = self.read_frame().unwrap();
+        assert_eq!(reply_id, id);
+
+        reply
+    }
+}
+
+fn start() -> rpc::ServerHandle {
+    let srv = Server::new();
+
+    srv.run(
+        EchoService.get_processor(),
+        TcpServerTransport::new(Address::from_str("127.0.0.1:0")).unwrap(),
+    ).unwrap()
+}
+
+#[test]
+fn unsupported_version() {
+    let handle = start();
+
+    // The server answers with the oldest version it supports and closes the
+    // connection.
+    let (mut peer, welcome) = RawPeer::connect(handle.get_addr(), 0);
+    assert_eq!(welcome, b"\x01\x00\x01");
+    assert!(peer.read_frame().is_none());
+}
+
+#[test]
+fn rejected_by_server() {
+    // Server rejecting the version of every client.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = Address::Socket(listener.local_addr().unwrap());
+
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut peer = RawPeer { stream };
+
+        peer.read_frame().unwrap();
+        peer.write_frame(0, b"\x01\x00\x02");
+    });
+
+    let c = EchoClient::new(TcpClientTransport::new(addr));
+    match c.echo(String::from("deadbeef")) {
+        Err(TestError::Call(CallError::Transport(Error::UnsupportedVersion(1)))) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn bad_request() {
+    let handle = start();
+
+    let (mut peer, welcome) = RawPeer::connect(handle.get_addr(), 1);
+    assert_eq!(welcome, b"\x00\x00\x01\x04json");
+
+    // The request is too short to hold its timeout.
+    let reply = peer.call(1, b"\x00");
+    assert_eq!(reply[0], 4);
+    assert!(!reply[1..].is_empty());
+
+    // The connection can still be used by the next requests, made of the
+    // timeout, the empty metadata and the message.
+    let mut request = 0u64.to_be_bytes().to_vec();
+    request.extend_from_slice(&0u32.to_be_bytes());
+    request.extend_from_slice(br#"{"Echo":"deadbeef"}"#);
+
+    let reply = peer.call(2, &request);
+    assert_eq!(reply, b"\x00\x00\x00\x00\x00{\"Echo\":\"deadbeef\"}");
+}